@@ -0,0 +1,158 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Arbitrary size unsigned integer, with just the operations needed by the
+/// puzzles: digits in base 2^32, least significant first, without zeros at
+/// the end so that every number has a single representation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        let mut result = Self { digits: vec![value as u32, (value >> 32) as u32] };
+        result.normalize();
+        result
+    }
+}
+
+impl BigUint {
+    fn normalize(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = carry
+                + self.digits.get(i).copied().unwrap_or(0) as u64
+                + other.digits.get(i).copied().unwrap_or(0) as u64;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        let mut result = Self { digits };
+        result.normalize();
+        result
+    }
+
+    /// `self - other`, or `None` if the result would be negative
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut digits = Vec::with_capacity(self.digits.len());
+        let mut borrow = 0i64;
+        for i in 0..self.digits.len() {
+            let mut difference = self.digits[i] as i64 - other.digits.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(difference as u32);
+        }
+        let mut result = Self { digits };
+        result.normalize();
+        Some(result)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.digits.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        let mut result = Self { digits };
+        result.normalize();
+        result
+    }
+
+    /// quotient and remainder of the division by a non zero `u64`
+    pub fn div_rem(&self, divisor: u64) -> (Self, u64) {
+        assert!(divisor != 0, "division by zero");
+        let mut digits = vec![0u32; self.digits.len()];
+        let mut remainder = 0u128;
+        for i in (0..self.digits.len()).rev() {
+            let current = (remainder << 32) | self.digits[i] as u128;
+            digits[i] = (current / divisor as u128) as u32;
+            remainder = current % divisor as u128;
+        }
+        let mut result = Self { digits };
+        result.normalize();
+        (result, remainder as u64)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // groups of 9 decimal digits, least significant first
+        const BASE: u64 = 1_000_000_000;
+        let mut groups = Vec::new();
+        let mut value = self.clone();
+        while !value.is_zero() {
+            let (quotient, remainder) = value.div_rem(BASE);
+            groups.push(remainder);
+            value = quotient;
+        }
+        write!(f, "{}", groups.pop().unwrap())?;
+        for group in groups.iter().rev() {
+            write!(f, "{:09}", group)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigUint;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_matches_u128() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            let (a, b) = (rng.next_u64(), rng.next_u64() >> rng.range(0, 63));
+            let (big_a, big_b) = (BigUint::from(a), BigUint::from(b));
+            let divisor = rng.range(1, u64::MAX);
+
+            assert_eq!(big_a.add(&big_b).to_string(), (a as u128 + b as u128).to_string());
+            assert_eq!(big_a.mul(&big_b).to_string(), (a as u128 * b as u128).to_string());
+            assert_eq!(big_a.checked_sub(&big_b).map(|r| r.to_string()), a.checked_sub(b).map(|r| r.to_string()));
+            let (quotient, remainder) = big_a.mul(&big_b).div_rem(divisor);
+            assert_eq!(quotient.to_string(), (a as u128 * b as u128 / divisor as u128).to_string());
+            assert_eq!(remainder as u128, a as u128 * b as u128 % divisor as u128);
+        }
+    }
+}
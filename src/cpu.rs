@@ -0,0 +1,247 @@
+use crate::problem::AoCResult;
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
+
+pub const REGISTER_NAMES: [char; 4] = ['x', 'y', 'z', 'w'];
+
+/// value of all the registers, `x` starts at 1 and the others at 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers(pub [i64; REGISTER_NAMES.len()]);
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self([1, 0, 0, 0])
+    }
+}
+
+impl Registers {
+    pub fn x(&self) -> i64 {
+        self.0[0]
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in REGISTER_NAMES.iter().zip(self.0.iter()).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}={:<4}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_register(name: &str) -> AoCResult<usize> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => REGISTER_NAMES
+            .iter()
+            .position(|r| *r == c)
+            .ok_or_else(|| format!("unknown register: {}", name)),
+        _ => Err(format!("unknown register: {}", name)),
+    }
+}
+
+/// an instruction of the program: it takes a number of cycles, and changes
+/// the registers at the end of the last one
+pub trait Instruction: Debug + fmt::Display {
+    fn cycles(&self) -> usize;
+    fn execute(&self, registers: &mut Registers);
+}
+
+#[derive(Debug)]
+pub struct Noop;
+
+impl Instruction for Noop {
+    fn cycles(&self) -> usize {
+        1
+    }
+
+    fn execute(&self, _registers: &mut Registers) {}
+}
+
+impl fmt::Display for Noop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "noop")
+    }
+}
+
+/// `addx V` and the same for the other registers
+#[derive(Debug)]
+pub struct Add {
+    pub register: usize,
+    pub value: i64,
+}
+
+impl Instruction for Add {
+    fn cycles(&self) -> usize {
+        2
+    }
+
+    fn execute(&self, registers: &mut Registers) {
+        registers.0[self.register] += self.value;
+    }
+}
+
+impl fmt::Display for Add {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "add{} {}", REGISTER_NAMES[self.register], self.value)
+    }
+}
+
+pub type Program = Vec<Box<dyn Instruction>>;
+
+pub fn parse_instruction(s: &str) -> AoCResult<Box<dyn Instruction>> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    match parts.as_slice() {
+        ["noop"] => Ok(Box::new(Noop)),
+        [op, value] if op.len() > 3 && op.starts_with("add") => Ok(Box::new(Add {
+            register: parse_register(&op[3..])?,
+            value: value.parse().map_err(|_| format!("invalid value: {}", value))?,
+        })),
+        _ => Err(format!("invalid instruction: {}", s)),
+    }
+}
+
+/// parses a line of source code, where `;` starts a comment; empty lines have
+/// no instruction
+pub fn parse_line(line: &str) -> AoCResult<Option<Box<dyn Instruction>>> {
+    let code = line.split(';').next().unwrap_or("").trim();
+    if code.is_empty() {
+        return Ok(None);
+    }
+    parse_instruction(code).map(Some)
+}
+
+pub fn assemble(source: &str) -> AoCResult<Program> {
+    let mut program = Program::new();
+    for (i, line) in source.lines().enumerate() {
+        if let Some(instruction) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))? {
+            program.push(instruction);
+        }
+    }
+    Ok(program)
+}
+
+/// source code of a program, without comments
+pub fn listing(program: &[Box<dyn Instruction>]) -> String {
+    let lines: Vec<String> = program.iter().map(|i| i.to_string()).collect();
+    lines.join("\n")
+}
+
+/// source code of a program, with the cycles taken by each instruction and
+/// the registers it changes as comments
+pub fn disassemble(program: &[Box<dyn Instruction>]) -> String {
+    let mut lines = Vec::new();
+    let mut registers = Registers::default();
+    let mut cycle = 1;
+    for instruction in program {
+        let cycles = instruction.cycles();
+        let mut comment = match cycles {
+            1 => format!("cycle {}", cycle),
+            n => format!("cycles {}-{}", cycle, cycle + n - 1),
+        };
+        let before = registers;
+        instruction.execute(&mut registers);
+        for (i, name) in REGISTER_NAMES.iter().enumerate() {
+            if before.0[i] != registers.0[i] {
+                comment += &format!(", {}: {} -> {}", name, before.0[i], registers.0[i]);
+            }
+        }
+        lines.push(format!("{:<12} ; {}", instruction.to_string(), comment));
+        cycle += cycles;
+    }
+    lines.join("\n")
+}
+
+/// state of the CPU in a clock cycle
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    /// number of the cycle, starting from 1
+    pub cycle: usize,
+    /// index of the instruction being executed
+    pub pc: usize,
+    /// cycle of the instruction being executed, starting from 1
+    pub instruction_cycle: usize,
+    pub during: Registers,
+    pub after: Registers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// stop during the given cycle
+    Cycle(usize),
+    /// stop during the first cycle of the instruction with the given index
+    Instruction(usize),
+}
+
+/// Runs a program one cycle at a time: iterating over it yields the state of
+/// every cycle until the program ends.
+#[derive(Debug)]
+pub struct Cpu<'a> {
+    program: &'a [Box<dyn Instruction>],
+    registers: Registers,
+    pc: usize,
+    cycle: usize,
+    /// cycle of the current instruction being executed, starting from 0
+    instruction_cycle: usize,
+    breakpoints: HashSet<Breakpoint>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Box<dyn Instruction>]) -> Self {
+        Self {
+            program,
+            registers: Registers::default(),
+            pc: 0,
+            cycle: 0,
+            instruction_cycle: 0,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.insert(breakpoint);
+    }
+
+    /// runs until a breakpoint is hit, returning the cycle where it stopped,
+    /// or until the end of the program
+    pub fn run(&mut self) -> Option<Step> {
+        while let Some(step) = self.next() {
+            let hit = self.breakpoints.contains(&Breakpoint::Cycle(step.cycle))
+                || (step.instruction_cycle == 1 && self.breakpoints.contains(&Breakpoint::Instruction(step.pc)));
+            if hit {
+                return Some(step);
+            }
+        }
+        None
+    }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let instruction = self.program.get(self.pc)?;
+        self.cycle += 1;
+        let during = self.registers;
+        let pc = self.pc;
+
+        self.instruction_cycle += 1;
+        let instruction_cycle = self.instruction_cycle;
+        if self.instruction_cycle >= instruction.cycles() {
+            instruction.execute(&mut self.registers);
+            self.instruction_cycle = 0;
+            self.pc += 1;
+        }
+
+        Some(Step {
+            cycle: self.cycle,
+            pc,
+            instruction_cycle,
+            during,
+            after: self.registers,
+        })
+    }
+}
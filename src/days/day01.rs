@@ -1,46 +1,120 @@
-use crate::problem::AoCProblem;
+use crate::problem::{parse_positive, AoCProblem, AoCResult};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const DEFAULT_TOP: usize = 3;
+
+/// calories carried by a single elf, identified by its position in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Group {
+    calories: u32,
+    index: usize,
+}
 
 #[derive(Debug)]
 pub struct AoCDay1 {
-    groups: Vec<Vec<u32>>,
+    top: usize,
+    /// min-heap holding the `top` groups with most calories seen so far
+    best: BinaryHeap<Reverse<Group>>,
+    current: Option<Group>,
+    groups_count: usize,
 }
 
 impl Default for AoCDay1 {
     fn default() -> Self {
         Self {
-            groups: vec![Vec::new()],
+            top: DEFAULT_TOP,
+            best: BinaryHeap::new(),
+            current: None,
+            groups_count: 0,
         }
     }
 }
 
+fn push_bounded(heap: &mut BinaryHeap<Reverse<Group>>, group: Group, max_len: usize) {
+    heap.push(Reverse(group));
+    if heap.len() > max_len {
+        heap.pop();
+    }
+}
+
+impl AoCDay1 {
+    /// best `n` groups, sorted by decreasing calories
+    fn top_groups(&self, n: usize) -> AoCResult<Vec<Group>> {
+        let mut heap = self.best.clone();
+        if let Some(group) = self.current {
+            push_bounded(&mut heap, group, self.top);
+        }
+        if heap.len() < n {
+            return Err(format!("requested top {} elves, but only {} in input", n, heap.len()));
+        }
+
+        let mut groups: Vec<Group> = heap.into_sorted_vec().into_iter().map(|Reverse(g)| g).collect();
+        groups.truncate(n);
+        Ok(groups)
+    }
+}
+
 impl AoCProblem for AoCDay1 {
-    fn parse_line(&mut self, line: String) {
-        match line.parse() {
-            Ok(value) => {
-                self.groups.last_mut().unwrap().push(value);
-            }
-            Err(_) => {
-                self.groups.push(Vec::new());
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        let line = line.trim();
+        if line.is_empty() {
+            if let Some(group) = self.current.take() {
+                push_bounded(&mut self.best, group, self.top);
             }
+            return Ok(());
         }
+
+        let value: u32 = line.parse().map_err(|_| format!("invalid calories value: {}", line))?;
+        if self.current.is_none() {
+            self.current = Some(Group { calories: 0, index: self.groups_count });
+            self.groups_count += 1;
+        }
+        let group = self.current.as_mut().unwrap();
+        group.calories = group.calories
+            .checked_add(value)
+            .ok_or_else(|| format!("calories overflow for elf {}", group.index + 1))?;
+        Ok(())
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "top" => self.top = parse_positive(key, value)?,
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        Ok(self.top_groups(1)?[0].calories.to_string())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        // each elf fits in a u32, but the total of several may not
+        Ok(self.top_groups(self.top)?.iter().map(|g| g.calories as u64).sum::<u64>().to_string())
     }
 
-    fn solve_part1(&self) -> String {
-        self.groups
-            .iter()
-            .map(|el| el.iter().fold(0u32, |acc, el| acc + el))
-            .fold(0, |acc, val| if val > acc { val } else { acc })
-            .to_string()
+    fn report(&self) -> AoCResult<String> {
+        let mut lines = vec![format!("top {} elves:", self.top)];
+        for (rank, group) in self.top_groups(self.top)?.iter().enumerate() {
+            lines.push(format!("{:>3}. elf {} carrying {} calories", rank + 1, group.index + 1, group.calories));
+        }
+        Ok(lines.join("\n"))
     }
+}
 
-    fn solve_part2(&self) -> String {
-        let mut values: Vec<u32> = self
-            .groups
-            .iter()
-            .map(|el| el.iter().fold(0u32, |acc, el| acc + el))
-            .collect();
-        values.sort_by(|a, b| b.cmp(a));
+#[cfg(test)]
+mod tests {
+    use super::AoCDay1;
+    use crate::problem::AoCProblem;
 
-        (values[0] + values[1] + values[2]).to_string()
+    #[test]
+    fn test_total_larger_than_u32() {
+        let mut problem = AoCDay1::default();
+        problem.set_option("top", "2").unwrap();
+        for line in ["3000000000", "", "3000000000"] {
+            problem.parse_line(line.into()).unwrap();
+        }
+        assert_eq!(problem.solve_part2(), Ok("6000000000".to_string()));
     }
 }
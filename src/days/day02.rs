@@ -1,180 +1,433 @@
-use crate::problem::AoCProblem;
+use crate::problem::{AoCProblem, AoCResult};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Move {
-    Rock,
-    Paper,
-    Scissors,
+/// index of a move in the cyclic order of the game rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Move(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Outcome {
+    Player1Win,
+    Player2Win,
+    Draw,
+}
+
+impl Outcome {
+    /// outcomes in the order they are listed in the symbol and score tables,
+    /// from the point of view of player 2 (loss, draw, win)
+    const ALL: [Outcome; 3] = [Outcome::Player1Win, Outcome::Draw, Outcome::Player2Win];
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|o| o == self).unwrap()
+    }
+}
+
+/// Rules of a cyclic game with an odd number of moves: each move beats the
+/// half of the other moves that come right before it in the cycle, so for
+/// Rock-Paper-Scissors every move beats the one before it, and for
+/// Rock-Spock-Paper-Lizard-Scissors the two before it.
+#[derive(Clone, Debug)]
+struct Rules {
+    moves: Vec<String>,
+    move_scores: Vec<u32>,
+    player_1_symbols: Vec<char>,
+    player_2_symbols: Vec<char>,
+    /// symbols for (loss, draw, win) of player 2
+    outcome_symbols: [char; 3],
+    /// scores for (loss, draw, win) of player 2
+    outcome_scores: [u32; 3],
 }
 
-impl Move {
-    const MOVE_BEATS: [(Move, Move); 3] = [
-        (Move::Rock, Move::Scissors),
-        (Move::Paper, Move::Rock),
-        (Move::Scissors, Move::Paper),
-    ];
+impl Default for Rules {
+    fn default() -> Self {
+        Self::rock_paper_scissors()
+    }
+}
 
-    fn parse_player_1(c: char) -> Self {
-        match c {
-            'A' => Move::Rock,
-            'B' => Move::Paper,
-            'C' => Move::Scissors,
-            _ => unreachable!(),
+impl Rules {
+    fn rock_paper_scissors() -> Self {
+        Self {
+            moves: vec!["Rock".into(), "Paper".into(), "Scissors".into()],
+            move_scores: vec![1, 2, 3],
+            player_1_symbols: vec!['A', 'B', 'C'],
+            player_2_symbols: vec!['X', 'Y', 'Z'],
+            outcome_symbols: ['X', 'Y', 'Z'],
+            outcome_scores: [0, 3, 6],
         }
     }
 
-    fn parse_player_2(c: char) -> Self {
-        match c {
-            'X' => Move::Rock,
-            'Y' => Move::Paper,
-            'Z' => Move::Scissors,
-            _ => unreachable!(),
+    fn rock_paper_scissors_lizard_spock() -> Self {
+        Self {
+            moves: vec!["Rock".into(), "Spock".into(), "Paper".into(), "Lizard".into(), "Scissors".into()],
+            move_scores: vec![1, 5, 2, 4, 3],
+            player_1_symbols: vec!['A', 'B', 'C', 'D', 'E'],
+            player_2_symbols: vec!['V', 'W', 'X', 'Y', 'Z'],
+            outcome_symbols: ['X', 'Y', 'Z'],
+            outcome_scores: [0, 3, 6],
         }
     }
 
-    fn score(&self) -> u32 {
-        match self {
-            Move::Rock => 1,
-            Move::Paper => 2,
-            Move::Scissors => 3,
+    fn validate(&self) -> AoCResult<()> {
+        let n = self.moves.len();
+        if n < 3 || n.is_multiple_of(2) {
+            return Err(format!("a cyclic game needs an odd number of moves (at least 3), got {}", n));
         }
+        if self.move_scores.len() != n {
+            return Err(format!("expected {} move scores, got {}", n, self.move_scores.len()));
+        }
+        if self.player_1_symbols.len() != n || self.player_2_symbols.len() != n {
+            return Err(format!("expected {} symbols for each player", n));
+        }
+        Ok(())
     }
 
-    fn beats(&self) -> Self {
-        Self::MOVE_BEATS.iter().find(|(m1, _m2)| m1 == self).unwrap().1
+    fn beats(&self, a: Move, b: Move) -> bool {
+        let n = self.moves.len();
+        (1..=n / 2).contains(&((a.0 + n - b.0) % n))
     }
 
-    fn looses(&self) -> Self {
-        Self::MOVE_BEATS.iter().find(|(_m1, m2)| m2 == self).unwrap().0
+    fn outcome(&self, player_1: Move, player_2: Move) -> Outcome {
+        if player_1 == player_2 {
+            Outcome::Draw
+        } else if self.beats(player_1, player_2) {
+            Outcome::Player1Win
+        } else {
+            Outcome::Player2Win
+        }
+    }
+
+    /// move of player 2 giving the requested outcome; when more than one move
+    /// does, the one with the highest score is chosen
+    fn force_outcome(&self, player_1: Move, outcome: Outcome) -> Move {
+        (0..self.moves.len())
+            .map(Move)
+            .filter(|m| self.outcome(player_1, *m) == outcome)
+            .max_by_key(|m| (self.move_scores[m.0], Reverse(m.0)))
+            .unwrap()
+    }
+
+    fn score(&self, player_1: Move, player_2: Move) -> u32 {
+        self.move_scores[player_2.0] + self.outcome_scores[self.outcome(player_1, player_2).index()]
+    }
+
+    fn parse_player_1(&self, c: char) -> AoCResult<Move> {
+        self.player_1_symbols
+            .iter()
+            .position(|s| *s == c)
+            .map(Move)
+            .ok_or_else(|| format!("invalid symbol for player 1: {}", c))
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Outcome {
-    Player1Win,
-    Player2Win,
-    Draw,
+/// A way of reading the second column of the strategy guide: either as the
+/// move to play or as the outcome to obtain.
+#[derive(Clone, Debug)]
+enum Interpretation {
+    Moves(HashMap<char, Move>),
+    Outcomes(HashMap<char, Outcome>),
 }
 
-impl Outcome {
-    fn parse(c: char) -> Self {
-        match c {
-            'X' => Outcome::Player1Win,
-            'Y' => Outcome::Draw,
-            'Z' => Outcome::Player2Win,
-            _ => unreachable!(),
-        }
+impl Interpretation {
+    fn describe(&self, rules: &Rules) -> String {
+        let mut parts: Vec<String> = match self {
+            Interpretation::Moves(map) => map
+                .iter()
+                .map(|(c, m)| format!("{} = {}", c, rules.moves[m.0]))
+                .collect(),
+            Interpretation::Outcomes(map) => map
+                .iter()
+                .map(|(c, o)| format!("{} = {}", c, ["lose", "draw", "win"][o.index()]))
+                .collect(),
+        };
+        parts.sort();
+        parts.join(", ")
     }
+}
 
-    fn score(&self) -> u32 {
-        match self {
-            Outcome::Player1Win => 0,
-            Outcome::Draw => 3,
-            Outcome::Player2Win => 6,
+/// every ordering of the given elements
+fn permutations<T: Clone>(elements: &[T]) -> Vec<Vec<T>> {
+    if elements.len() <= 1 {
+        return vec![elements.to_vec()];
+    }
+    let mut result = Vec::new();
+    for i in 0..elements.len() {
+        let mut rest = elements.to_vec();
+        let first = rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, first.clone());
+            result.push(permutation);
         }
     }
+    result
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Round {
+    player_1: Move,
+    player_2: Move,
+    outcome: Outcome,
+    score: u32,
+    cumulative_score: u32,
+}
+
+/// result of playing all the rounds of the strategy guide
+#[derive(Debug, Default)]
+struct Tournament {
+    rounds: Vec<Round>,
+    /// number of rounds lost, drawn and won by player 2
+    outcomes: [usize; 3],
+    score: u32,
+}
+
+impl Tournament {
+    fn play(&mut self, rules: &Rules, player_1: Move, player_2: Move) {
+        let outcome = rules.outcome(player_1, player_2);
+        let score = rules.score(player_1, player_2);
+        self.score += score;
+        self.outcomes[outcome.index()] += 1;
+        self.rounds.push(Round {
+            player_1,
+            player_2,
+            outcome,
+            score,
+            cumulative_score: self.score,
+        });
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "score {}, {} won, {} drawn, {} lost",
+            self.score, self.outcomes[2], self.outcomes[1], self.outcomes[0],
+        )
+    }
 }
 
+const DEFAULT_REPORT_ROUNDS: usize = 20;
+/// trying every reading of the second column plays the strategy guide n! times
+const MAX_OPTIMISE_MOVES: usize = 7;
+
 #[derive(Debug)]
-struct Game {
-    player_1_move: Option<Move>,
-    player_2_move: Option<Move>,
+pub struct AoCDay2 {
+    rules: Rules,
+    moves: Vec<(char, char)>,
+    /// number of rounds listed in the report, `None` to list all of them
+    report_rounds: Option<usize>,
 }
 
-impl Game {
-    fn new() -> Self {
+impl Default for AoCDay2 {
+    fn default() -> Self {
         Self {
-            player_1_move: None,
-            player_2_move: None,
+            rules: Rules::default(),
+            moves: Vec::new(),
+            report_rounds: Some(DEFAULT_REPORT_ROUNDS),
         }
     }
+}
 
-    fn play_player_1(&mut self, mov: Move) {
-        self.player_1_move = Some(mov);
+impl AoCDay2 {
+    fn simulate(&self, interpretation: &Interpretation) -> AoCResult<Tournament> {
+        self.rules.validate()?;
+        let mut tournament = Tournament::default();
+        for (pl_1, pl_2) in self.moves.iter() {
+            let player_1 = self.rules.parse_player_1(*pl_1)?;
+            let player_2 = match interpretation {
+                Interpretation::Moves(map) => *map.get(pl_2)
+                    .ok_or_else(|| format!("invalid move symbol for player 2: {}", pl_2))?,
+                Interpretation::Outcomes(map) => self.rules.force_outcome(player_1, *map.get(pl_2)
+                    .ok_or_else(|| format!("invalid outcome symbol: {}", pl_2))?),
+            };
+            tournament.play(&self.rules, player_1, player_2);
+        }
+        Ok(tournament)
     }
 
-    fn play_player_2(&mut self, mov: Move) {
-        self.player_2_move = Some(mov);
+    fn score(&self, interpretation: &Interpretation) -> AoCResult<u32> {
+        Ok(self.simulate(interpretation)?.score)
     }
 
-    fn force_outcome(&mut self, outcome: Outcome) {
-        if let Some(mov) = self.player_1_move {
-            self.player_2_move = match outcome {
-                // to draw, simply to the same move
-                Outcome::Draw => Some(mov),
-                // for the player one to win, do the move that is beated
-                Outcome::Player1Win => Some(mov.beats()),
-                // otherwise, do the other move
-                Outcome::Player2Win => Some(mov.looses()),
-            }
-        }
+    fn moves_interpretation(&self) -> Interpretation {
+        Interpretation::Moves(self.rules.player_2_symbols.iter().enumerate().map(|(i, c)| (*c, Move(i))).collect())
     }
 
-    fn outcome(&self) -> Option<Outcome> {
-        if let (Some(m1), Some(m2)) = (self.player_1_move, self.player_2_move) {
-            if m1 == m2 {
-                Some(Outcome::Draw)
-            } else if m1.beats() == m2 {
-                Some(Outcome::Player1Win)
-            } else {
-                Some(Outcome::Player2Win)
-            }
-        } else {
-            None
-        }
+    fn outcomes_interpretation(&self) -> Interpretation {
+        Interpretation::Outcomes(self.rules.outcome_symbols.iter().zip(Outcome::ALL).map(|(c, o)| (*c, o)).collect())
     }
 
-    fn score(&self) -> Option<u32> {
-        if let (Some(m2), Some(outcome)) = (self.player_2_move, self.outcome()) {
-            Some(m2.score() + outcome.score())
-        } else {
-            None
+    /// all the possible readings of the second column, each with its total score,
+    /// sorted from the best to the worst
+    fn optimise(&self) -> AoCResult<Vec<(Interpretation, u32)>> {
+        if self.rules.moves.len() > MAX_OPTIMISE_MOVES {
+            return Err(format!(
+                "too many moves to try every interpretation: {} (at most {})",
+                self.rules.moves.len(), MAX_OPTIMISE_MOVES,
+            ));
         }
+        let mut candidates = Vec::new();
+        let moves: Vec<Move> = (0..self.rules.moves.len()).map(Move).collect();
+        for permutation in permutations(&moves) {
+            candidates.push(Interpretation::Moves(self.rules.player_2_symbols.iter().copied().zip(permutation).collect()));
+        }
+        for permutation in permutations(&Outcome::ALL) {
+            candidates.push(Interpretation::Outcomes(self.rules.outcome_symbols.iter().copied().zip(permutation).collect()));
+        }
+
+        let mut result = Vec::new();
+        for candidate in candidates {
+            let score = self.score(&candidate)?;
+            result.push((candidate, score));
+        }
+        result.sort_by_key(|(_, score)| Reverse(*score));
+        Ok(result)
     }
 }
 
-#[derive(Debug)]
-pub struct AoCDay2 {
-    moves: Vec<(char, char)>
+/// parses a comma separated list option, such as `moves=Rock,Paper,Scissors`
+fn parse_list<T: std::str::FromStr>(value: &str) -> AoCResult<Vec<T>> {
+    value
+        .split(',')
+        .map(|v| v.trim().parse().map_err(|_| format!("invalid value: {}", v)))
+        .collect()
 }
 
-impl Default for AoCDay2 {
-    fn default() -> Self {
-        Self {
-            moves: Vec::new()
-        }
-    }
+fn parse_triple<T: std::str::FromStr + Copy>(value: &str) -> AoCResult<[T; 3]> {
+    let values: Vec<T> = parse_list(value)?;
+    values.try_into().map_err(|_| format!("expected 3 values, got {}", value))
 }
 
 impl AoCProblem for AoCDay2 {
-    fn parse_line(&mut self, line: String) {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
         let chars: Vec<char> = line.chars().collect();
+        if chars.len() != 3 || chars[1] != ' ' {
+            return Err(format!("invalid strategy line: {}", line));
+        }
+        self.rules.parse_player_1(chars[0])?;
+        if !self.rules.player_2_symbols.contains(&chars[2]) && !self.rules.outcome_symbols.contains(&chars[2]) {
+            return Err(format!("invalid symbol for player 2: {}", chars[2]));
+        }
         self.moves.push((chars[0], chars[2]));
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        let mut score = 0;
-        for (pl_1, pl_2) in self.moves.iter() {
-            let mut game = Game::new();
-            game.play_player_1(Move::parse_player_1(*pl_1));
-            game.play_player_2(Move::parse_player_2(*pl_2));
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "game" => {
+                self.rules = match value {
+                    "rps" => Rules::rock_paper_scissors(),
+                    "rpsls" => Rules::rock_paper_scissors_lizard_spock(),
+                    _ => return Err(format!("unknown game: {} (expected rps or rpsls)", value)),
+                };
+            },
+            "moves" => self.rules.moves = parse_list(value)?,
+            "move_scores" => self.rules.move_scores = parse_list(value)?,
+            "player1" => self.rules.player_1_symbols = parse_list(value)?,
+            "player2" => self.rules.player_2_symbols = parse_list(value)?,
+            "outcomes" => self.rules.outcome_symbols = parse_triple(value)?,
+            "outcome_scores" => self.rules.outcome_scores = parse_triple(value)?,
+            "report_rounds" => {
+                self.report_rounds = match value {
+                    "all" => None,
+                    _ => Some(value.parse().map_err(|_| format!("invalid number of rounds: {}", value))?),
+                };
+            },
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
+    }
 
-            score += game.score().unwrap();
+    fn solve_part1(&self) -> AoCResult<String> {
+        Ok(self.score(&self.moves_interpretation())?.to_string())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        Ok(self.score(&self.outcomes_interpretation())?.to_string())
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let rules = &self.rules;
+        let part_1_interpretation = self.moves_interpretation();
+        let part_2_interpretation = self.outcomes_interpretation();
+        let part_1 = self.simulate(&part_1_interpretation)?;
+        let part_2 = self.simulate(&part_2_interpretation)?;
+
+        let mut lines = vec![
+            format!("part 1, as moves ({}): {}", part_1_interpretation.describe(rules), part_1.summary()),
+            format!("part 2, as outcomes ({}): {}", part_2_interpretation.describe(rules), part_2.summary()),
+        ];
+
+        let (mut better, mut worse, mut equal) = (0, 0, 0);
+        for (r1, r2) in part_1.rounds.iter().zip(part_2.rounds.iter()) {
+            match r2.score.cmp(&r1.score) {
+                Ordering::Greater => better += 1,
+                Ordering::Less => worse += 1,
+                Ordering::Equal => equal += 1,
+            }
+        }
+        lines.push(format!(
+            "part 2 scores {} points {} than part 1: better in {} rounds, worse in {}, equal in {}",
+            part_1.score.abs_diff(part_2.score),
+            if part_2.score >= part_1.score { "more" } else { "less" },
+            better, worse, equal,
+        ));
+
+        let outcome_name = |o: Outcome| ["lose", "draw", "win"][o.index()];
+        let shown_rounds = self.report_rounds.unwrap_or(self.moves.len()).min(self.moves.len());
+        lines.push(format!("\nfirst {} of {} rounds (player 1 move | player 2 move, outcome, score, cumulative score):", shown_rounds, self.moves.len()));
+        for (i, (r1, r2)) in part_1.rounds.iter().zip(part_2.rounds.iter()).take(shown_rounds).enumerate() {
+            lines.push(format!(
+                "{:>5}. {:<9} | part 1: {:<9} {:<4} {:>3} {:>7} | part 2: {:<9} {:<4} {:>3} {:>7}",
+                i + 1, rules.moves[r1.player_1.0],
+                rules.moves[r1.player_2.0], outcome_name(r1.outcome), r1.score, r1.cumulative_score,
+                rules.moves[r2.player_2.0], outcome_name(r2.outcome), r2.score, r2.cumulative_score,
+            ));
         }
 
-        score.to_string()
+        match self.optimise() {
+            Ok(ranking) => {
+                lines.push(format!("\nbest interpretations out of {}:", ranking.len()));
+                for (interpretation, score) in ranking.iter().take(5) {
+                    let kind = match interpretation {
+                        Interpretation::Moves(_) => "moves",
+                        Interpretation::Outcomes(_) => "outcomes",
+                    };
+                    lines.push(format!("{:>8} as {}: {}", score, kind, interpretation.describe(rules)));
+                }
+            },
+            Err(e) => lines.push(format!("\n{}", e)),
+        }
+        Ok(lines.join("\n"))
     }
+}
 
-    fn solve_part2(&self) -> String {
-        let mut score = 0;
-        for (pl_1, out) in self.moves.iter() {
-            let mut game = Game::new();
-            game.play_player_1(Move::parse_player_1(*pl_1));
-            game.force_outcome(Outcome::parse(*out));
+#[cfg(test)]
+mod tests {
+    use super::{Move, Rules};
 
-            score += game.score().unwrap();
+    #[test]
+    fn test_rock_paper_scissors_lizard_spock() {
+        let rules = Rules::rock_paper_scissors_lizard_spock();
+        let name = |m: &str| Move(rules.moves.iter().position(|n| n == m).unwrap());
+        let wins = [
+            ("Rock", ["Scissors", "Lizard"]),
+            ("Paper", ["Rock", "Spock"]),
+            ("Scissors", ["Paper", "Lizard"]),
+            ("Lizard", ["Paper", "Spock"]),
+            ("Spock", ["Rock", "Scissors"]),
+        ];
+        for (a, beaten) in wins {
+            for (b, _) in wins {
+                let expected = beaten.contains(&b);
+                assert_eq!(rules.beats(name(a), name(b)), expected, "{} vs {}", a, b);
+            }
         }
+    }
 
-        score.to_string()
+    #[test]
+    fn test_rock_paper_scissors() {
+        let rules = Rules::rock_paper_scissors();
+        assert!(rules.beats(Move(1), Move(0)));
+        assert!(rules.beats(Move(2), Move(1)));
+        assert!(rules.beats(Move(0), Move(2)));
+        assert!(!rules.beats(Move(0), Move(1)));
     }
 }
@@ -1,49 +1,129 @@
-use crate::problem::AoCProblem;
-use std::collections::HashSet;
+use crate::problem::{parse_positive, AoCProblem, AoCResult};
 
-#[derive(Default, Debug)]
+const DEFAULT_COMPARTMENTS: usize = 2;
+const DEFAULT_GROUP_SIZE: usize = 3;
+
+/// Set of item types, one bit for each of the 52 letters: bit `priority - 1`
+/// is set when the item is present.
+type ItemSet = u64;
+
+fn priority(c: char) -> AoCResult<u32> {
+    match c {
+        'a'..='z' => Ok(1 + c as u32 - 'a' as u32),
+        'A'..='Z' => Ok(27 + c as u32 - 'A' as u32),
+        _ => Err(format!("invalid item: {:?}", c)),
+    }
+}
+
+fn item(priority: u32) -> char {
+    match priority {
+        1..=26 => (b'a' + (priority - 1) as u8) as char,
+        27..=52 => (b'A' + (priority - 27) as u8) as char,
+        _ => unreachable!(),
+    }
+}
+
+fn item_set(items: &str) -> AoCResult<ItemSet> {
+    items.chars().try_fold(0, |set, c| Ok(set | 1 << (priority(c)? - 1)))
+}
+
+fn intersection(sets: impl Iterator<Item = ItemSet>) -> ItemSet {
+    sets.fold(ItemSet::MAX, |acc, set| acc & set)
+}
+
+fn priorities(set: ItemSet) -> impl Iterator<Item = u32> {
+    (1..=52).filter(move |p| set & (1 << (p - 1)) != 0)
+}
+
+#[derive(Debug)]
+struct Rucksack {
+    compartments: Vec<ItemSet>,
+}
+
+impl Rucksack {
+    fn items(&self) -> ItemSet {
+        self.compartments.iter().fold(0, |acc, set| acc | set)
+    }
+}
+
+#[derive(Debug)]
 pub struct AoCDay3 {
-    lines: Vec<String>,
+    rucksacks: Vec<Rucksack>,
+    compartments: usize,
+    group_size: usize,
 }
 
-fn priority(c: char) -> u32 {
-    match c {
-        'a'..='z' => 1 + c as u32 - 'a' as u32,
-        'A'..='Z' => 27 + c as u32 - 'A' as u32,
-        _ => unreachable!()
+impl Default for AoCDay3 {
+    fn default() -> Self {
+        Self {
+            rucksacks: Vec::new(),
+            compartments: DEFAULT_COMPARTMENTS,
+            group_size: DEFAULT_GROUP_SIZE,
+        }
     }
 }
 
-fn common_chars(a: &str, b: &str) -> HashSet<char> {
-    a.chars().filter(|c| b.contains(*c)).collect()
+impl AoCDay3 {
+    /// items in common to all the rucksacks of each group
+    fn badges(&self) -> AoCResult<Vec<ItemSet>> {
+        if !self.rucksacks.len().is_multiple_of(self.group_size) {
+            return Err(format!("{} rucksacks can't be split in groups of {}", self.rucksacks.len(), self.group_size));
+        }
+        Ok(self.rucksacks
+            .chunks(self.group_size)
+            .map(|group| intersection(group.iter().map(Rucksack::items)))
+            .collect())
+    }
 }
 
 impl AoCProblem for AoCDay3 {
-    fn parse_line(&mut self, line: String) {
-        self.lines.push(line)
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        // validate the items first, so that the line can be split by bytes
+        item_set(&line)?;
+        if !line.len().is_multiple_of(self.compartments) {
+            return Err(format!("{} items can't be split in {} compartments", line.len(), self.compartments));
+        }
+        let size = line.len() / self.compartments;
+        let compartments = (0..self.compartments)
+            .map(|i| item_set(&line[i * size..(i + 1) * size]))
+            .collect::<AoCResult<Vec<ItemSet>>>()?;
+        self.rucksacks.push(Rucksack { compartments });
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        let mut result = 0;
-        for line in &self.lines {
-            let (sack1, sack2) = line.split_at(line.len() / 2);
-            assert_eq!(sack1.len(), sack2.len());
-
-            result += common_chars(sack1, sack2).into_iter().map(|c| priority(c) as i64).sum::<i64>();
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "compartments" => self.compartments = parse_positive(key, value)?,
+            "group_size" => self.group_size = parse_positive(key, value)?,
+            _ => return Err(format!("unknown option: {}", key)),
         }
-        result.to_string()
-    }
-
-    fn solve_part2(&self) -> String {
-        assert_eq!(self.lines.len() % 3, 0);
-        let mut result = 0;
-        for i in 0..(self.lines.len() / 3) {
-            for c in common_chars(self.lines[i * 3].as_str(), self.lines[i * 3 + 1].as_str()) {
-                if self.lines[i * 3 + 2].contains(c) {
-                    result += priority(c) as i64;
-                }
-            }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        let result: u32 = self.rucksacks
+            .iter()
+            .map(|r| priorities(intersection(r.compartments.iter().copied())).sum::<u32>())
+            .sum();
+        Ok(result.to_string())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        let result: u32 = self.badges()?.into_iter().map(|badge| priorities(badge).sum::<u32>()).sum();
+        Ok(result.to_string())
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let mut lines = Vec::new();
+        for (i, badge) in self.badges()?.into_iter().enumerate() {
+            let items: Vec<String> = priorities(badge).map(|p| format!("{} ({})", item(p), p)).collect();
+            let first = i * self.group_size + 1;
+            lines.push(format!(
+                "group {} (rucksacks {}-{}): {}",
+                i + 1, first, first + self.group_size - 1,
+                if items.is_empty() { "no badge".into() } else { items.join(", ") },
+            ));
         }
-        result.to_string()
+        Ok(lines.join("\n"))
     }
 }
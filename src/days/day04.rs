@@ -1,4 +1,4 @@
-use crate::problem::AoCProblem;
+use crate::problem::{AoCProblem, AoCResult};
 
 type Pair = (u32, u32);
 
@@ -29,7 +29,7 @@ fn overlap(i1: &Pair, i2: &Pair) -> bool {
 }
 
 impl AoCProblem for AoCDay4 {
-    fn parse_line(&mut self, line: String) {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
         // ex. line fmt: 71-89,66-70
         let parts: Vec<&str> = line.split(',').collect();
         assert_eq!(parts.len(), 2);
@@ -41,13 +41,14 @@ impl AoCProblem for AoCDay4 {
         }
 
         self.input.push((parse_pair(parts[0]), parse_pair(parts[1])));
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        self.input.iter().filter(|(i1, i2)| full_overlap(i1, i2)).count().to_string()
+    fn solve_part1(&self) -> AoCResult<String> {
+        Ok(self.input.iter().filter(|(i1, i2)| full_overlap(i1, i2)).count().to_string())
     }
 
-    fn solve_part2(&self) -> String {
-        self.input.iter().filter(|(i1, i2)| overlap(i1, i2)).count().to_string()
+    fn solve_part2(&self) -> AoCResult<String> {
+        Ok(self.input.iter().filter(|(i1, i2)| overlap(i1, i2)).count().to_string())
     }
 }
@@ -1,4 +1,7 @@
-use crate::problem::AoCProblem;
+use crate::problem::{parse_positive, Animation, AoCProblem, AoCResult};
+use std::fmt::{self, Debug};
+use std::fs;
+use std::time::Duration;
 
 #[derive(Debug, Copy, Clone)]
 struct Move {
@@ -7,64 +10,332 @@ struct Move {
     to: usize,
 }
 
-#[derive(Default, Debug)]
-pub struct AoCDay5 {
-    stacks: [Vec<char>; 10],
-    moves: Vec<Move>
+/// a crane moving crates between stacks, top of the stack is the last element
+trait Crane: Debug {
+    fn move_crates(&self, from: &mut Vec<String>, to: &mut Vec<String>, elements: usize);
 }
 
-impl AoCProblem for AoCDay5 {
-    fn parse_line(&mut self, line: String) {
-        if line.starts_with("move") {
-            let parts: Vec<&str> = line.split(" ").collect();
-            self.moves.push(Move {
-                elements: parts[1].parse().unwrap(),
-                from: parts[3].parse::<usize>().unwrap() - 1,
-                to: parts[5].parse::<usize>().unwrap() - 1,
-            })
-        } else {
-            for (i, c) in line.chars().enumerate() {   
-                if i % 4 == 1 && c.is_alphabetic() {
-                    self.stacks[i / 4].insert(0, c);
+/// moves one crate at a time, reversing their order
+#[derive(Debug)]
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn move_crates(&self, from: &mut Vec<String>, to: &mut Vec<String>, elements: usize) {
+        for _ in 0..elements {
+            to.push(from.pop().unwrap());
+        }
+    }
+}
+
+/// moves all the crates at once, keeping their order
+#[derive(Debug)]
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn move_crates(&self, from: &mut Vec<String>, to: &mut Vec<String>, elements: usize) {
+        let start = from.len() - elements;
+        to.extend(from.drain(start..));
+    }
+}
+
+/// moves up to `capacity` crates at once, keeping the order of each batch
+#[derive(Debug)]
+struct BatchCrane {
+    capacity: usize,
+}
+
+impl Crane for BatchCrane {
+    fn move_crates(&self, from: &mut Vec<String>, to: &mut Vec<String>, elements: usize) {
+        let mut remaining = elements;
+        while remaining > 0 {
+            let batch = remaining.min(self.capacity);
+            CrateMover9001.move_crates(from, to, batch);
+            remaining -= batch;
+        }
+    }
+}
+
+fn parse_crane(model: &str) -> AoCResult<Box<dyn Crane>> {
+    match model {
+        "9000" => Ok(Box::new(CrateMover9000)),
+        "9001" => Ok(Box::new(CrateMover9001)),
+        _ => match model.strip_prefix("batch-").map(str::parse) {
+            Some(Ok(capacity)) if capacity > 0 => Ok(Box::new(BatchCrane { capacity })),
+            _ => Err(format!("unknown crane model: {} (expected 9000, 9001 or batch-N)", model)),
+        },
+    }
+}
+
+/// whitespace separated words of a diagram line, each with the first and last column it occupies
+fn words(line: &str) -> Vec<(usize, usize, &str)> {
+    let mut result = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                result.push((s, i - 1, &line[s..i]));
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    result
+}
+
+#[derive(Debug, Clone, Default)]
+struct Stacks {
+    labels: Vec<String>,
+    stacks: Vec<Vec<String>>,
+}
+
+impl Stacks {
+    /// builds the stacks from the diagram rows, top row first, and the row of stack labels
+    fn parse(rows: &[String], label_row: &str) -> AoCResult<Self> {
+        let labels = words(label_row);
+        let mut stacks = vec![Vec::new(); labels.len()];
+        let rows: Vec<(usize, &String)> = rows.iter().enumerate().filter(|(_, r)| !r.trim().is_empty()).collect();
+        for (level, (n, row)) in rows.into_iter().rev().enumerate() {
+            for (start, end, token) in words(row) {
+                let name = token
+                    .strip_prefix('[')
+                    .and_then(|t| t.strip_suffix(']'))
+                    .filter(|t| !t.is_empty())
+                    .ok_or_else(|| format!("diagram row {}: invalid crate {}", n + 1, token))?;
+                let stack = labels
+                    .iter()
+                    .position(|(l_start, l_end, _)| *l_start <= end && *l_end >= start)
+                    .ok_or_else(|| format!("diagram row {}: crate {} is not above any stack", n + 1, token))?;
+                if stacks[stack].len() != level {
+                    return Err(format!("diagram row {}: crate {} is floating above stack {}", n + 1, token, labels[stack].2));
                 }
+                stacks[stack].push(name.to_string());
             }
         }
+        Ok(Self {
+            labels: labels.iter().map(|(_, _, l)| l.to_string()).collect(),
+            stacks,
+        })
     }
 
-    fn solve_part1(&self) -> String {
-        let mut stacks = self.stacks.clone(); 
-        for m in &self.moves {
-            for _ in 0..m.elements {
-                let el = stacks[m.from].pop().unwrap();
-                stacks[m.to].push(el);
-            }
+    fn index(&self, label: &str) -> AoCResult<usize> {
+        self.labels
+            .iter()
+            .position(|l| l == label)
+            .ok_or_else(|| format!("unknown stack {}", label))
+    }
+
+    fn apply(&mut self, crane: &dyn Crane, m: &Move) -> AoCResult<()> {
+        let available = self.stacks[m.from].len();
+        if available < m.elements {
+            return Err(format!(
+                "can't move {} crates from stack {}, it only has {}",
+                m.elements, self.labels[m.from], available,
+            ));
         }
+        if m.from != m.to {
+            let (from, to) = if m.from < m.to {
+                let (a, b) = self.stacks.split_at_mut(m.to);
+                (&mut a[m.from], &mut b[0])
+            } else {
+                let (a, b) = self.stacks.split_at_mut(m.from);
+                (&mut b[0], &mut a[m.to])
+            };
+            crane.move_crates(from, to, m.elements);
+        }
+        Ok(())
+    }
 
-        let mut result = String::new();
-        for s in stacks {
-            if s.len() > 0 {
-                result.push(s[s.len() - 1]);
-            }
+    fn top(&self) -> String {
+        self.stacks.iter().filter_map(|s| s.last()).cloned().collect()
+    }
+
+    fn describe_move(&self, m: &Move) -> String {
+        format!("move {} from {} to {}", m.elements, self.labels[m.from], self.labels[m.to])
+    }
+}
+
+impl fmt::Display for Stacks {
+    /// draws the stacks in the same format of the puzzle input
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths: Vec<usize> = self.stacks
+            .iter()
+            .zip(self.labels.iter())
+            .map(|(stack, label)| stack.iter().map(|c| c.chars().count() + 2).chain([label.chars().count(), 3]).max().unwrap())
+            .collect();
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self.stacks
+                .iter()
+                .zip(widths.iter())
+                .map(|(stack, width)| match stack.get(level) {
+                    Some(name) => format!("{:^width$}", format!("[{}]", name), width = width),
+                    None => " ".repeat(*width),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
         }
-        result
+        let labels: Vec<String> = self.labels
+            .iter()
+            .zip(widths.iter())
+            .map(|(label, width)| format!("{:^width$}", label, width = width))
+            .collect();
+        write!(f, "{}", labels.join(" "))
     }
+}
 
-    fn solve_part2(&self) -> String {
-        let mut stacks = self.stacks.clone(); 
-        for m in &self.moves {
-            let start = stacks[m.from].len() - m.elements;
-            let end = stacks[m.from].len();
-            let mut el: Vec<char> = stacks[m.from].drain(start..end).collect();
-            
-            stacks[m.to].append(&mut el);
+/// options of the step by step replay of the moves shown in the report
+#[derive(Debug)]
+struct Replay {
+    /// part whose crane is used for the replay
+    part: usize,
+    /// render a frame every this many moves
+    every: usize,
+    /// pause between frames, when animating them in the terminal
+    delay: Option<Duration>,
+    /// file where the frames are written
+    file: Option<String>,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            part: 1,
+            every: 1,
+            delay: None,
+            file: None,
         }
+    }
+}
+
+#[derive(Debug)]
+pub struct AoCDay5 {
+    /// diagram rows, kept until the row with the stack labels is found
+    rows: Vec<String>,
+    stacks: Option<Stacks>,
+    moves: Vec<Move>,
+    crane_1: Box<dyn Crane>,
+    crane_2: Box<dyn Crane>,
+    replay: Replay,
+}
 
-        let mut result = String::new();
-        for s in stacks {
-            if s.len() > 0 {
-                result.push(s[s.len() - 1]);
+impl Default for AoCDay5 {
+    fn default() -> Self {
+        Self {
+            rows: Vec::new(),
+            stacks: None,
+            moves: Vec::new(),
+            crane_1: Box::new(CrateMover9000),
+            crane_2: Box::new(CrateMover9001),
+            replay: Replay::default(),
+        }
+    }
+}
+
+impl AoCDay5 {
+    fn solve(&self, crane: &dyn Crane) -> AoCResult<String> {
+        let mut stacks = self.stacks.clone().ok_or("missing stack labels row")?;
+        for (step, m) in self.moves.iter().enumerate() {
+            stacks.apply(crane, m).map_err(|e| format!("step {}: {}", step + 1, e))?;
+        }
+        Ok(stacks.top())
+    }
+
+    fn replay_crane(&self) -> &dyn Crane {
+        if self.replay.part == 1 { self.crane_1.as_ref() } else { self.crane_2.as_ref() }
+    }
+
+    /// diagrams of the stacks before the first move, after every `replay.every`
+    /// moves and after the last one; an invalid move ends the replay with an error frame
+    fn replay_frames(&self, crane: &dyn Crane) -> AoCResult<Vec<String>> {
+        let mut stacks = self.stacks.clone().ok_or("missing stack labels row")?;
+        let mut frames = vec![format!("initial state\n{}", stacks)];
+        for (step, m) in self.moves.iter().enumerate() {
+            let description = stacks.describe_move(m);
+            if let Err(e) = stacks.apply(crane, m) {
+                frames.push(format!("step {}/{}: {}\nerror: {}\n{}", step + 1, self.moves.len(), description, e, stacks));
+                break;
+            }
+            if (step + 1) % self.replay.every == 0 || step + 1 == self.moves.len() {
+                frames.push(format!("step {}/{}: {}\n{}", step + 1, self.moves.len(), description, stacks));
             }
         }
-        result
+        Ok(frames)
+    }
+}
+
+impl AoCProblem for AoCDay5 {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        match &self.stacks {
+            Some(stacks) => {
+                if line.trim().is_empty() {
+                    return Ok(());
+                }
+                let (elements, from, to) = sscanf::sscanf!(line.trim(), "move {usize} from {str} to {str}")
+                    .map_err(|_| format!("invalid move: {}", line))?;
+                let m = Move {
+                    elements,
+                    from: stacks.index(from)?,
+                    to: stacks.index(to)?,
+                };
+                self.moves.push(m);
+            },
+            None => {
+                let is_label_row = !line.trim().is_empty() && !line.contains('[');
+                if is_label_row {
+                    self.stacks = Some(Stacks::parse(&self.rows, &line)?);
+                    self.rows.clear();
+                } else {
+                    self.rows.push(line);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "crane1" => self.crane_1 = parse_crane(value)?,
+            "crane2" => self.crane_2 = parse_crane(value)?,
+            "replay_part" => {
+                self.replay.part = match value {
+                    "1" => 1,
+                    "2" => 2,
+                    _ => return Err(format!("invalid replay_part: {}", value)),
+                };
+            },
+            "replay_every" => self.replay.every = parse_positive(key, value)?,
+            "replay_delay" => self.replay.delay = Some(Duration::from_millis(parse_positive(key, value)? as u64)),
+            "replay_file" => self.replay.file = Some(value.to_string()),
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        self.solve(self.crane_1.as_ref())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        self.solve(self.crane_2.as_ref())
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let frames = self.replay_frames(self.replay_crane())?;
+        match &self.replay.file {
+            Some(path) => {
+                fs::write(path, frames.join("\n\n") + "\n").map_err(|e| format!("error writing {}: {}", path, e))?;
+                Ok(format!("{} frames written to {}", frames.len(), path))
+            },
+            None if self.replay.delay.is_some() => Ok(format!("replay of {} frames:", frames.len())),
+            None => Ok(frames.join("\n\n")),
+        }
+    }
+
+    fn animation(&self) -> AoCResult<Option<Animation>> {
+        match self.replay.delay {
+            Some(delay) => Ok(Some(Animation { frames: self.replay_frames(self.replay_crane())?, delay })),
+            None => Ok(None),
+        }
     }
 }
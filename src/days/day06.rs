@@ -1,64 +1,227 @@
-use crate::problem::AoCProblem;
+use crate::problem::{parse_positive, AoCProblem, AoCResult};
+use std::collections::VecDeque;
+use std::io::{BufRead, Read};
 
-#[derive(Debug, Default)]
-pub struct AoCDay6 {
-    input: String
+const DEFAULT_PACKET_WINDOW: usize = 4;
+const DEFAULT_MESSAGE_WINDOW: usize = 14;
+
+/// Finds windows of `size` distinct symbols in a stream of bytes, keeping the
+/// count of each symbol in the window so that every byte is processed in O(1).
+#[derive(Debug)]
+struct MarkerDetector {
+    size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    distinct: usize,
+    position: usize,
 }
 
-impl AoCProblem for AoCDay6 {
-    fn parse_line(&mut self, line: String) {
-        self.input = line;
+impl MarkerDetector {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            window: VecDeque::with_capacity(size + 1),
+            counts: [0; 256],
+            distinct: 0,
+            position: 0,
+        }
+    }
+
+    /// adds a symbol to the window, returns true if the window is now a marker
+    fn push(&mut self, symbol: u8) -> bool {
+        self.position += 1;
+        self.window.push_back(symbol);
+        self.counts[symbol as usize] += 1;
+        if self.counts[symbol as usize] == 1 {
+            self.distinct += 1;
+        }
+
+        if self.window.len() > self.size {
+            let removed = self.window.pop_front().unwrap() as usize;
+            self.counts[removed] -= 1;
+            if self.counts[removed] == 0 {
+                self.distinct -= 1;
+            }
+        }
+
+        self.window.len() == self.size && self.distinct == self.size
     }
+}
+
+#[derive(Debug)]
+struct Datastream {
+    packet_markers: Vec<usize>,
+    message_markers: Vec<usize>,
+}
+
+/// positions (number of symbols read) at which the packet and message markers
+/// end, reading the datastream one symbol at a time
+#[derive(Debug)]
+struct DatastreamReader {
+    packet: MarkerDetector,
+    message: MarkerDetector,
+    datastream: Datastream,
+}
+
+impl DatastreamReader {
+    fn new(packet_window: usize, message_window: usize) -> Self {
+        Self {
+            packet: MarkerDetector::new(packet_window),
+            message: MarkerDetector::new(message_window),
+            datastream: Datastream { packet_markers: Vec::new(), message_markers: Vec::new() },
+        }
+    }
+
+    fn push(&mut self, symbol: u8) {
+        if self.packet.push(symbol) {
+            self.datastream.packet_markers.push(self.packet.position);
+        }
+        if self.message.push(symbol) {
+            self.datastream.message_markers.push(self.message.position);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.packet.position == 0
+    }
+}
+
+/// each line of the input is a separate datastream
+#[derive(Debug)]
+pub struct AoCDay6 {
+    packet_window: usize,
+    message_window: usize,
+    datastreams: Vec<Datastream>,
+}
 
-    fn solve_part1(&self) -> String {
-        find_fist_marker(self.input.as_str(), 4).unwrap().to_string()
+impl Default for AoCDay6 {
+    fn default() -> Self {
+        Self {
+            packet_window: DEFAULT_PACKET_WINDOW,
+            message_window: DEFAULT_MESSAGE_WINDOW,
+            datastreams: Vec::new(),
+        }
     }
+}
 
-    fn solve_part2(&self) -> String {
-        find_fist_marker(self.input.as_str(), 14).unwrap().to_string()
+fn first_markers(datastreams: &[Datastream], markers: impl Fn(&Datastream) -> &Vec<usize>) -> AoCResult<String> {
+    let mut result = Vec::new();
+    for (i, datastream) in datastreams.iter().enumerate() {
+        match markers(datastream).first() {
+            Some(position) => result.push(position.to_string()),
+            None => return Err(format!("no marker found in datastream {}", i + 1)),
+        }
     }
+    Ok(result.join(","))
 }
 
-fn find_fist_marker(line: &str, min_chars: usize) -> Option<usize> {
-    let mut queue: Vec<char> = Vec::new();
-    for (i, c) in line.chars().enumerate() {
-        assert!(queue.len() < min_chars);
-        queue.push(c);
-    
-        let mut elements: Vec<char> = queue.clone();
-        elements.sort();
-        elements.dedup();
-        if elements.len() == min_chars {
-            return Some(i + 1);
+fn describe_markers(window: usize, markers: &[usize]) -> String {
+    let positions: Vec<String> = markers.iter().map(|p| p.to_string()).collect();
+    format!("  window {}: {} markers ending at {}", window, markers.len(), positions.join(", "))
+}
+
+impl AoCProblem for AoCDay6 {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        if line.is_empty() {
+            return Ok(());
+        }
+        let mut reader = DatastreamReader::new(self.packet_window, self.message_window);
+        line.bytes().for_each(|symbol| reader.push(symbol));
+        self.datastreams.push(reader.datastream);
+        Ok(())
+    }
+
+    /// the datastreams can be huge, so they are read byte by byte instead of
+    /// being split in lines
+    fn parse_reader(&mut self, reader: &mut dyn BufRead) -> AoCResult<()> {
+        let mut current = DatastreamReader::new(self.packet_window, self.message_window);
+        for symbol in reader.bytes() {
+            match symbol.map_err(|e| format!("datastream {}: {}", self.datastreams.len() + 1, e))? {
+                // blank lines don't start a datastream
+                b'\n' if current.is_empty() => {},
+                b'\n' => {
+                    let next = DatastreamReader::new(self.packet_window, self.message_window);
+                    self.datastreams.push(std::mem::replace(&mut current, next).datastream);
+                },
+                b'\r' => {},
+                symbol => current.push(symbol),
+            }
+        }
+        if !current.is_empty() {
+            self.datastreams.push(current.datastream);
         }
+        Ok(())
+    }
 
-        if queue.len() >= min_chars {
-            queue.remove(0);
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "packet_window" => self.packet_window = parse_positive(key, value)?,
+            "message_window" => self.message_window = parse_positive(key, value)?,
+            _ => return Err(format!("unknown option: {}", key)),
         }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        first_markers(&self.datastreams, |d| &d.packet_markers)
     }
 
-    None
+    fn solve_part2(&self) -> AoCResult<String> {
+        first_markers(&self.datastreams, |d| &d.message_markers)
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let mut lines = Vec::new();
+        for (i, datastream) in self.datastreams.iter().enumerate() {
+            lines.push(format!("datastream {}:", i + 1));
+            lines.push(describe_markers(self.packet_window, &datastream.packet_markers));
+            lines.push(describe_markers(self.message_window, &datastream.message_markers));
+        }
+        Ok(lines.join("\n"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::find_fist_marker;
+    use super::AoCDay6;
+    use crate::problem::AoCProblem;
+
+    const EXAMPLES: &str = "mjqjpqmgbljsphdztnvjfqwrcgsmlb
+bvwbjplbgvbhsrlpgdmjqwftvncz
+nppdvjthqldpwncqszvftbrmjlhg
+nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg
+zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw
+";
+
+    fn parse(input: &str) -> AoCDay6 {
+        let mut problem = AoCDay6::default();
+        problem.parse_reader(&mut input.as_bytes()).unwrap();
+        problem
+    }
 
     #[test]
     fn test_part_1() {
-        assert_eq!(find_fist_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 4), Some(7));
-        assert_eq!(find_fist_marker("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), Some(5));
-        assert_eq!(find_fist_marker("nppdvjthqldpwncqszvftbrmjlhg",4 ), Some(6));
-        assert_eq!(find_fist_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4), Some(10));
-        assert_eq!(find_fist_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4), Some(11));
+        assert_eq!(parse(EXAMPLES).solve_part1(), Ok("7,5,6,10,11".to_string()));
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let input = EXAMPLES.replace('\n', "\n\n");
+        assert_eq!(parse(&input).solve_part1(), Ok("7,5,6,10,11".to_string()));
     }
 
     #[test]
     fn test_part_2() {
-        assert_eq!(find_fist_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14), Some(19));
-        assert_eq!(find_fist_marker("bvwbjplbgvbhsrlpgdmjqwftvncz", 14), Some(23));
-        assert_eq!(find_fist_marker("nppdvjthqldpwncqszvftbrmjlhg", 14 ), Some(23));
-        assert_eq!(find_fist_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14), Some(29));
-        assert_eq!(find_fist_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14), Some(26));
+        assert_eq!(parse(EXAMPLES).solve_part2(), Ok("19,23,23,29,26".to_string()));
+    }
+
+    #[test]
+    fn test_all_markers() {
+        let mut problem = AoCDay6 { packet_window: 3, ..Default::default() };
+        problem.parse_reader(&mut "abcabc\r\naaaa".as_bytes()).unwrap();
+        problem.packet_window = 2;
+        problem.parse_reader(&mut "aabba".as_bytes()).unwrap();
+        let all: Vec<&Vec<usize>> = problem.datastreams.iter().map(|d| &d.packet_markers).collect();
+        assert_eq!(all, vec![&vec![3, 4, 5, 6], &vec![], &vec![3, 5]]);
     }
 }
@@ -1,169 +1,235 @@
-use crate::problem::AoCProblem;
-use std::{
-    cell::RefCell,
-    collections::HashMap,
-    rc::{Rc, Weak},
-};
-
-const MAX_SIZE: usize = 100_000;
-const TOTAL_SIZE: usize = 70_000_000;
-const REQUIRED_SPACE: usize = 30_000_000;
+use crate::filesystem::{FileSystem, NodeId};
+use crate::problem::{AoCProblem, AoCResult};
+use std::collections::HashSet;
+
+pub const MAX_SIZE: usize = 100_000;
+pub const TOTAL_SIZE: usize = 70_000_000;
+pub const REQUIRED_SPACE: usize = 30_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum View {
+    Tree,
+    Du,
+    DuHuman,
+}
 
 #[derive(Debug)]
-struct Directory {
-    entries: HashMap<String, Entry>,
-    parent: Weak<RefCell<Directory>>,
+pub struct AoCDay7 {
+    fs: FileSystem,
+    cwd: NodeId,
+    /// true while reading the output of `ls`
+    listing: bool,
+    /// directories whose content was listed at least once
+    listed: HashSet<NodeId>,
+    max_size: usize,
+    total_size: usize,
+    required_space: usize,
+    view: View,
+    /// size range of the directories listed in the report, instead of the view
+    find: Option<(usize, usize)>,
 }
 
-#[derive(Debug)]
-struct File {
-    size: usize,
+impl Default for AoCDay7 {
+    fn default() -> Self {
+        AoCDay7 {
+            fs: FileSystem::default(),
+            cwd: FileSystem::ROOT,
+            listing: false,
+            listed: HashSet::new(),
+            max_size: MAX_SIZE,
+            total_size: TOTAL_SIZE,
+            required_space: REQUIRED_SPACE,
+            view: View::Tree,
+            find: None,
+        }
+    }
 }
 
-#[derive(Debug)]
-enum Entry {
-    File(File),
-    Directory(Rc<RefCell<Directory>>),
+fn parse_size(value: &str) -> AoCResult<usize> {
+    value.parse().map_err(|_| format!("invalid size: {}", value))
 }
 
-#[derive(Debug)]
-pub struct AoCDay7 {
-    root: Rc<RefCell<Directory>>,
-    cwd: Option<Rc<RefCell<Directory>>>,
+/// parses a size range in the form `MIN-MAX`, where both ends are optional
+fn parse_range(value: &str) -> AoCResult<(usize, usize)> {
+    let (min, max) = value
+        .split_once('-')
+        .ok_or_else(|| format!("invalid range: {} (expected MIN-MAX)", value))?;
+    let min = if min.is_empty() { 0 } else { parse_size(min)? };
+    let max = if max.is_empty() { usize::MAX } else { parse_size(max)? };
+    Ok((min, max))
 }
 
-fn part_1_solve(max_size: usize, dir: &Rc<RefCell<Directory>>) -> (usize, usize) {
-    let mut size = 0;
-    let mut max = 0;
-    for (_, entry) in &dir.borrow().entries {
-        match entry {
-            Entry::File(f) => {
-                size += f.size;
-            }
-            Entry::Directory(d) => {
-                let (d_size, d_max) = part_1_solve(max_size, d);
-                size += d_size;
-                max += d_max;
-            }
-        }
+/// total size of the directories of at most `max_size`
+pub fn part_1_solve(fs: &FileSystem, max_size: usize) -> usize {
+    fs.find_directories(0, max_size).iter().map(|(_, size)| size).sum()
+}
+
+/// size of the smallest directory to delete to have `required_space` free, 0
+/// when there is already enough free space and nothing needs to be deleted
+pub fn part_2_solve(fs: &FileSystem, total_size: usize, required_space: usize) -> AoCResult<usize> {
+    let occupied_size = fs.sizes()[FileSystem::ROOT];
+    let free_space = total_size
+        .checked_sub(occupied_size)
+        .ok_or_else(|| format!("{} bytes used on a disk of {}", occupied_size, total_size))?;
+    if free_space >= required_space {
+        return Ok(0);
     }
-    if size < max_size {
-        max += size;
+    let to_free = required_space - free_space;
+    match fs.find_directories(to_free, usize::MAX).first() {
+        Some((_, size)) => Ok(*size),
+        None => Err(format!("no directory is large enough to free {} bytes", to_free)),
     }
-    (size, max)
 }
 
-fn part_2_solve(min_size: usize, dir: &Rc<RefCell<Directory>>) -> (usize, usize) {
-    let mut size = 0;
-    let mut min = usize::MAX;
-    for (_, entry) in &dir.borrow().entries {
-        match entry {
-            Entry::File(f) => {
-                size += f.size;
-            }
-            Entry::Directory(d) => {
-                let (d_size, d_min) = part_2_solve(min_size, d);
-                size += d_size;
-                if d_size > min_size {
-                    min = min.min(d_size);
-                }
-                min = min.min(d_min);
-            }
-        }
+impl AoCDay7 {
+    /// directories that were seen in a listing but never listed themselves,
+    /// whose size is unknown
+    fn unlisted(&self) -> Vec<NodeId> {
+        self.fs.directories().filter(|id| !self.listed.contains(id)).collect()
     }
-    (size, min)
-}
 
-impl Default for AoCDay7 {
-    fn default() -> Self {
-        let root = Rc::new_cyclic(|this| {
-            RefCell::new(Directory {
-                entries: HashMap::new(),
-                parent: this.clone(),
-            })
-        });
-        AoCDay7 {
-            root: root.clone(),
-            cwd: Some(root),
+    /// flags the answer when it's computed on a partial transcript
+    fn flag_partial(&self, answer: String) -> String {
+        match self.unlisted().len() {
+            0 => answer,
+            n => format!("{} (partial transcript, {} directories never listed)", answer, n),
         }
     }
 }
 
-impl AoCDay7 {
-    fn exists(&self, name: &str) -> bool {
-        self.cwd
-            .as_ref()
-            .unwrap()
-            .borrow_mut()
-            .entries
-            .contains_key(name)
+impl AoCProblem for AoCDay7 {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        let parts: Vec<&str> = line.split(' ').collect();
+        match parts.as_slice() {
+            ["$", "cd", path] => {
+                let dir = self.fs.resolve(self.cwd, path)?;
+                if !self.fs.is_dir(dir) {
+                    return Err(format!("can't cd into file {}", self.fs.path(dir)));
+                }
+                self.cwd = dir;
+                self.listing = false;
+            },
+            ["$", "ls"] => {
+                self.listing = true;
+                self.listed.insert(self.cwd);
+            },
+            ["$", ..] => return Err(format!("unknown command: {}", line)),
+            _ if !self.listing => return Err(format!("output outside of ls: {}", line)),
+            ["dir", name] => {
+                self.fs.mkdir(self.cwd, name)?;
+            },
+            [size, name] => {
+                self.fs.touch(self.cwd, name, parse_size(size)?)?;
+            },
+            _ => return Err(format!("invalid line: {}", line)),
+        }
+        Ok(())
     }
 
-    fn mkdir(&self, name: &str) {
-        self.cwd.as_ref().unwrap().borrow_mut().entries.insert(
-            name.into(),
-            Entry::Directory(Rc::new(RefCell::new(Directory {
-                entries: HashMap::new(),
-                parent: Rc::downgrade(&self.cwd.as_ref().unwrap()),
-            }))),
-        );
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "max_size" => self.max_size = parse_size(value)?,
+            "total_size" => self.total_size = parse_size(value)?,
+            "required_space" => self.required_space = parse_size(value)?,
+            "view" => {
+                self.view = match value {
+                    "tree" => View::Tree,
+                    "du" => View::Du,
+                    "du-h" => View::DuHuman,
+                    _ => return Err(format!("unknown view: {} (expected tree, du or du-h)", value)),
+                };
+            },
+            "find" => self.find = Some(parse_range(value)?),
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
     }
 
-    fn cd(&mut self, name: &str) {
-        let cwd = self.cwd.take();
-        match cwd.unwrap().borrow().entries.get(name) {
-            None => unreachable!(),
-            Some(Entry::File(_)) => panic!("try to CD to a file"),
-            Some(Entry::Directory(dir)) => {
-                self.cwd = Some(dir.clone());
-            }
-        }
+    fn solve_part1(&self) -> AoCResult<String> {
+        let result = part_1_solve(&self.fs, self.max_size);
+        Ok(self.flag_partial(result.to_string()))
     }
 
-    fn touch(&mut self, name: &str, size: usize) {
-        self.cwd
-            .as_ref()
-            .unwrap()
-            .borrow_mut()
-            .entries
-            .insert(name.into(), Entry::File(File { size }));
+    fn solve_part2(&self) -> AoCResult<String> {
+        let result = part_2_solve(&self.fs, self.total_size, self.required_space)?;
+        Ok(self.flag_partial(result.to_string()))
     }
-}
 
-impl AoCProblem for AoCDay7 {
-    fn parse_line(&mut self, line: String) {
-        let parts: Vec<&str> = line.split(' ').collect();
-        if parts[0] == "$" {
-            if parts[1] == "cd" {
-                if parts[2] == ".." {
-                    self.cwd = self.cwd.take().unwrap().borrow().parent.upgrade();
-                } else {
-                    let dir_name = parts[2];
-
-                    if !self.exists(dir_name) {
-                        self.mkdir(dir_name);
-                    }
-
-                    self.cd(dir_name);
-                }
-            }
+    fn report(&self) -> AoCResult<String> {
+        let mut result = if let Some((min, max)) = self.find {
+            let lines: Vec<String> = self.fs
+                .find_directories(min, max)
+                .into_iter()
+                .map(|(id, size)| format!("{}\t{}", size, self.fs.path(id)))
+                .collect();
+            lines.join("\n")
         } else {
-            if parts[0] != "dir" {
-                let size: usize = parts[0].parse().unwrap();
-                let file_name = parts[1];
-                self.touch(file_name, size);
+            match self.view {
+                View::Tree => self.fs.tree(),
+                View::Du => self.fs.du(false),
+                View::DuHuman => self.fs.du(true),
+            }
+        };
+        let unlisted = self.unlisted();
+        if !unlisted.is_empty() {
+            result += "\n\ndirectories never listed:";
+            for id in unlisted {
+                result += &format!("\n{}", self.fs.path(id));
             }
         }
+        Ok(result)
     }
+}
 
-    fn solve_part1(&self) -> String {
-        part_1_solve(MAX_SIZE, &self.root).1.to_string()
+#[cfg(test)]
+mod tests {
+    use super::{AoCDay7, MAX_SIZE, REQUIRED_SPACE, TOTAL_SIZE};
+    use crate::filesystem::FileSystem;
+    use crate::problem::AoCProblem;
+
+    fn parse(lines: &[&str]) -> Result<AoCDay7, String> {
+        let mut problem = AoCDay7::default();
+        for line in lines {
+            problem.parse_line(line.to_string())?;
+        }
+        Ok(problem)
+    }
+
+    #[test]
+    fn test_invalid_transcripts() {
+        let error = |lines: &[&str]| parse(lines).unwrap_err();
+        assert_eq!(error(&["$ cd /", "$ ls", "100 a.txt", "$ cd a.txt"]), "can't cd into file /a.txt");
+        assert_eq!(error(&["$ cd /", "100 a.txt"]), "output outside of ls: 100 a.txt");
+        assert_eq!(error(&["$ cd /", "$ ls", "dir a", "$ cd a", "100 b.txt"]), "output outside of ls: 100 b.txt");
+        assert_eq!(error(&["$ cd /", "$ cd .."]), "..: can't go above the root directory");
+        assert_eq!(
+            error(&["$ ls", "100 a.txt", "$ ls", "200 a.txt"]),
+            "/a.txt already exists with size 100, not 200"
+        );
     }
 
-    fn solve_part2(&self) -> String {
-        let occupied_size = part_1_solve(100_000, &self.root).0;
-        let free_space = TOTAL_SIZE - occupied_size;
-        let to_free = REQUIRED_SPACE - free_space;
-        part_2_solve(to_free, &self.root).1.to_string()
+    #[test]
+    fn test_partial_transcript() {
+        let mut problem = parse(&["$ cd /", "$ ls", "dir a", "dir b", "100 c.txt", "$ cd a", "$ ls", "50 d.txt"]).unwrap();
+        assert_eq!(problem.solve_part1(), Ok("200 (partial transcript, 1 directories never listed)".into()));
+
+        for line in ["$ cd /b", "$ ls"] {
+            problem.parse_line(line.into()).unwrap();
+        }
+        assert_eq!(problem.solve_part1(), Ok("200".into()));
+    }
+
+    #[test]
+    fn test_random_transcripts() {
+        for seed in 1..=5 {
+            let fs = FileSystem::random(seed, 200, 300, 300_000);
+            let mut problem = AoCDay7::default();
+            for line in fs.transcript().lines() {
+                problem.parse_line(line.into()).unwrap();
+            }
+            assert_eq!(problem.solve_part1(), Ok(fs.walk_part_1(MAX_SIZE).to_string()), "seed {}", seed);
+            let expected = fs.walk_part_2(TOTAL_SIZE, REQUIRED_SPACE).map(|size| size.to_string());
+            assert_eq!(problem.solve_part2(), expected, "seed {}", seed);
+        }
     }
 }
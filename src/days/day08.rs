@@ -1,113 +1,218 @@
-use crate::problem::AoCProblem;
-use std::collections::HashSet;
+use crate::problem::{parse_bool, AoCProblem, AoCResult};
+use std::fs;
 
-#[derive(Debug, Default)]
-pub struct AoCDay8 {
-    height: Vec<Vec<usize>>
+/// characters used for the scenic score heat map, from the lowest to the highest score
+const HEAT_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// visibility from outside the forest and scenic score of every tree
+#[derive(Debug)]
+struct Analysis {
+    visible: Vec<Vec<bool>>,
+    scenic_score: Vec<Vec<usize>>,
 }
 
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
+/// Looks from each tree of a line back towards the start of the line: returns
+/// whether the tree is visible from that edge and how many trees it can see.
+/// A stack keeps the trees that can still block the view of the next ones, in
+/// non increasing height order, so each tree is pushed and popped once.
+fn look_back(heights: &[u32]) -> Vec<(bool, usize)> {
+    let mut stack: Vec<usize> = Vec::new();
+    let mut result = Vec::with_capacity(heights.len());
+    for (i, height) in heights.iter().enumerate() {
+        while let Some(&top) = stack.last() {
+            if heights[top] >= *height {
+                break;
+            }
+            stack.pop();
+        }
+        result.push(match stack.last() {
+            // no tree as high as this one up to the edge
+            None => (true, i),
+            Some(&blocking) => (false, i - blocking),
+        });
+        stack.push(i);
+    }
+    result
 }
 
-impl AoCDay8 {
-    fn count_trees(&self, x: i32, y: i32, height: usize, dir: Direction) -> usize {
-        if y < 0 || x < 0 || y >= self.height.len() as i32 || x >= self.height[0].len() as i32 {
-            0
-        } else if self.height[y as usize][x as usize] >= height {
-            1
-        } else {
-            1 + match dir {
-                Direction::Up => self.count_trees(x, y - 1, height, dir),
-                Direction::Down => self.count_trees(x, y + 1, height, dir),
-                Direction::Left => self.count_trees(x - 1, y, height, dir),
-                Direction::Right => self.count_trees(x + 1, y, height, dir)
+impl Analysis {
+    /// coordinates of the tree with the highest scenic score
+    fn best_tree(&self) -> Option<(usize, usize)> {
+        let mut best = None;
+        for (y, row) in self.scenic_score.iter().enumerate() {
+            for (x, score) in row.iter().enumerate() {
+                if best.is_none_or(|(bx, by): (usize, usize)| *score > self.scenic_score[by][bx]) {
+                    best = Some((x, y));
+                }
             }
         }
+        best
     }
 
-    fn view_score(&self, x: i32, y: i32) -> usize {
-        let height = self.height[y as usize][x as usize];
-        let mut score = 1;
-        score *= self.count_trees(x + 1, y, height, Direction::Right);
-        score *= self.count_trees(x - 1, y, height, Direction::Left);
-        score *= self.count_trees(x, y + 1, height, Direction::Down);
-        score *= self.count_trees(x, y - 1, height, Direction::Up);
-        score
+    /// scenic score scaled to `0..=255`, on a logarithmic scale since few trees have a high score
+    fn heat(&self, x: usize, y: usize, max_score: usize) -> u8 {
+        if max_score == 0 {
+            return 0;
+        }
+        let value = (1.0 + self.scenic_score[y][x] as f64).ln() / (1.0 + max_score as f64).ln();
+        (value * 255.0).round() as u8
     }
 }
 
-impl AoCProblem for AoCDay8 {
-    fn parse_line(&mut self, line: String) {
-        let mut v = Vec::new();
-        for c in line.chars() {
-            v.push(c as usize - '0' as usize)
+#[derive(Debug, Default)]
+pub struct AoCDay8 {
+    height: Vec<Vec<u32>>,
+    /// use ANSI colors in the report
+    color: bool,
+    /// file where the scenic score heat map is written as a plain PGM image
+    pgm_file: Option<String>,
+}
+
+impl AoCDay8 {
+    /// coordinates of the trees of every line of sight, in each of the four directions
+    fn lines_of_sight(&self) -> Vec<Vec<(usize, usize)>> {
+        let max_y = self.height.len();
+        let max_x = self.height.first().map_or(0, Vec::len);
+        let mut lines = Vec::new();
+        for y in 0..max_y {
+            let row: Vec<(usize, usize)> = (0..max_x).map(|x| (x, y)).collect();
+            lines.push(row.iter().rev().copied().collect());
+            lines.push(row);
+        }
+        for x in 0..max_x {
+            let column: Vec<(usize, usize)> = (0..max_y).map(|y| (x, y)).collect();
+            lines.push(column.iter().rev().copied().collect());
+            lines.push(column);
         }
-        self.height.push(v);
+        lines
     }
 
-    fn solve_part1(&self) -> String {
+    fn analyse(&self) -> Analysis {
         let max_y = self.height.len();
-        let max_x  = self.height[0].len();
-        let mut visible_trees: HashSet<(usize, usize)> = HashSet::new();
+        let max_x = self.height.first().map_or(0, Vec::len);
+        let mut visible = vec![vec![false; max_x]; max_y];
+        let mut scenic_score = vec![vec![1; max_x]; max_y];
 
-        for y in 0..max_y {
-            let mut height = -1;
-            for x in 0..max_x {
-                let current = self.height[y][x] as i32;
-                // more high than tree near in this direction: visible
-                if current > height {
-                    visible_trees.insert((x, y));
-                }
-                height = height.max(current);
+        for line in self.lines_of_sight() {
+            let heights: Vec<u32> = line.iter().map(|(x, y)| self.height[*y][*x]).collect();
+            for ((x, y), (is_visible, distance)) in line.into_iter().zip(look_back(&heights)) {
+                visible[y][x] |= is_visible;
+                scenic_score[y][x] *= distance;
             }
+        }
+
+        Analysis { visible, scenic_score }
+    }
 
-            height = -1;
-            for x in (0..max_x).rev() {
-                let current = self.height[y][x] as i32;
-                // more high than tree near in this direction: visible
-                if current > height {
-                    visible_trees.insert((x, y));
+    fn render_visible(&self, analysis: &Analysis) -> String {
+        let mut lines = Vec::new();
+        for (y, row) in self.height.iter().enumerate() {
+            let mut line = String::new();
+            for (x, height) in row.iter().enumerate() {
+                let c = char::from_digit(*height, 10).unwrap_or('+');
+                match (analysis.visible[y][x], self.color) {
+                    (true, true) => line += &format!("\x1b[1;32m{}\x1b[0m", c),
+                    (false, true) => line += &format!("\x1b[2m{}\x1b[0m", c),
+                    (true, false) => line.push(c),
+                    (false, false) => line.push('.'),
                 }
-                height = height.max(current);
             }
+            lines.push(line);
         }
+        lines.join("\n")
+    }
 
-        for x in 0..max_x {
-            let mut height = -1;
-            for y in 0..max_y {
-                let current = self.height[y][x] as i32;
-                // more high than tree near in this direction: visible
-                if current > height {
-                    visible_trees.insert((x, y));
+    fn render_heat_map(&self, analysis: &Analysis, max_score: usize) -> String {
+        let mut lines = Vec::new();
+        for (y, row) in analysis.scenic_score.iter().enumerate() {
+            let mut line = String::new();
+            for x in 0..row.len() {
+                let heat = analysis.heat(x, y, max_score);
+                if self.color {
+                    // 24 levels grayscale of the 256 colors palette
+                    line += &format!("\x1b[48;5;{}m \x1b[0m", 232 + heat as usize * 23 / 255);
+                } else {
+                    line.push(HEAT_RAMP[heat as usize * (HEAT_RAMP.len() - 1) / 255] as char);
                 }
-                height = height.max(current);
             }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
 
-            height = -1;
-            for y in (0..max_y).rev() {
-                let current = self.height[y][x] as i32;
-                // more high than tree near in this direction: visible
-                if current > height {
-                    visible_trees.insert((x, y));
-                }
-                height = height.max(current);
+    fn write_pgm(&self, path: &str, analysis: &Analysis, max_score: usize) -> AoCResult<()> {
+        let height = analysis.scenic_score.len();
+        let width = analysis.scenic_score.first().map_or(0, Vec::len);
+        let mut image = format!("P2\n{} {}\n255\n", width, height);
+        for y in 0..height {
+            let row: Vec<String> = (0..width).map(|x| analysis.heat(x, y, max_score).to_string()).collect();
+            image += &row.join(" ");
+            image.push('\n');
+        }
+        fs::write(path, image).map_err(|e| format!("error writing {}: {}", path, e))
+    }
+}
+
+impl AoCProblem for AoCDay8 {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        // single digit heights are written next to each other, larger ones separated by spaces or commas
+        let row: Vec<u32> = if line.contains([' ', ',']) {
+            line.split([' ', ','])
+                .filter(|h| !h.is_empty())
+                .map(|h| h.parse().map_err(|_| format!("invalid height: {}", h)))
+                .collect::<AoCResult<_>>()?
+        } else {
+            line.chars()
+                .map(|c| c.to_digit(10).ok_or_else(|| format!("invalid height: {}", c)))
+                .collect::<AoCResult<_>>()?
+        };
+        if let Some(first) = self.height.first() {
+            if first.len() != row.len() {
+                return Err(format!("expected {} trees in each row, got {}", first.len(), row.len()));
             }
         }
+        self.height.push(row);
+        Ok(())
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "color" => self.color = parse_bool(key, value)?,
+            "pgm" => self.pgm_file = Some(value.to_string()),
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
+    }
 
-        visible_trees.len().to_string()
+    fn solve_part1(&self) -> AoCResult<String> {
+        let analysis = self.analyse();
+        let result = analysis.visible.iter().flatten().filter(|v| **v).count();
+        Ok(result.to_string())
     }
 
-    fn solve_part2(&self) -> String {
-        let mut result = 0;
-        for y in 0..self.height.len() {
-            for x in 0..self.height[0].len() {
-                result = result.max(self.view_score(x as i32, y as i32));
-            }
+    fn solve_part2(&self) -> AoCResult<String> {
+        let analysis = self.analyse();
+        let result = analysis.scenic_score.iter().flatten().max().copied().unwrap_or(0);
+        Ok(result.to_string())
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let analysis = self.analyse();
+        let (x, y) = analysis.best_tree().ok_or("empty forest")?;
+        let max_score = analysis.scenic_score[y][x];
+        let mut lines = vec![
+            format!("best tree at x={}, y={} (height {}, scenic score {})", x, y, self.height[y][x], max_score),
+            String::new(),
+            "visible trees:".into(),
+            self.render_visible(&analysis),
+            String::new(),
+            "scenic score (logarithmic scale):".into(),
+            self.render_heat_map(&analysis, max_score),
+        ];
+        if let Some(path) = &self.pgm_file {
+            self.write_pgm(path, &analysis, max_score)?;
+            lines.push(format!("\nheat map written to {}", path));
         }
-        result.to_string()
+        Ok(lines.join("\n"))
     }
 }
@@ -1,30 +1,33 @@
-use crate::problem::AoCProblem;
+use crate::problem::{parse_positive, AoCProblem, AoCResult};
 use std::{collections::HashSet, hash::Hash};
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+#[derive(Clone, Copy, Debug)]
+struct Move {
+    dx: i32,
+    dy: i32,
+    len: u32,
 }
 
-impl From<&str> for Direction {
-    fn from(s: &str) -> Self {
-        match s {
-            "U" => Direction::Up,
-            "D" => Direction::Down,
-            "L" => Direction::Left,
-            "R" => Direction::Right,
-            _ => panic!("invalid string")
+/// parses a direction made of up to one vertical and one horizontal step,
+/// such as `U`, `R` or the diagonal `UR`
+fn parse_direction(s: &str) -> AoCResult<(i32, i32)> {
+    let (mut dx, mut dy) = (None, None);
+    for c in s.chars() {
+        let (axis, delta) = match c {
+            'U' => (&mut dy, 1),
+            'D' => (&mut dy, -1),
+            'R' => (&mut dx, 1),
+            'L' => (&mut dx, -1),
+            _ => return Err(format!("invalid direction: {}", s)),
+        };
+        if axis.replace(delta).is_some() {
+            return Err(format!("invalid direction: {}", s));
         }
     }
-}
-
-#[derive(Clone, Copy, Debug)]
-struct Move {
-    direction: Direction,
-    len: i32,
+    match (dx, dy) {
+        (None, None) => Err("missing direction".into()),
+        (dx, dy) => Ok((dx.unwrap_or(0), dy.unwrap_or(0))),
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
@@ -33,93 +36,312 @@ struct Point {
     y: i32,
 }
 
+impl Point {
+    fn touches(&self, other: Point) -> bool {
+        (self.x - other.x).abs() <= 1 && (self.y - other.y).abs() <= 1
+    }
+}
+
+/// how a knot moves when the knot before it is no longer touching it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum FollowRule {
+    /// one step towards the head on both axes, moving diagonally if needed
+    #[default]
+    Diagonal,
+    /// orthogonal steps only, along the axis where the head is further
+    Orthogonal,
+}
+
+impl FollowRule {
+    /// moves the tail until it touches the head, adding every position it
+    /// goes through to `visited`: with the orthogonal rule a tail can take
+    /// several steps to catch up with a head that moved diagonally
+    fn follow(&self, head: Point, mut tail: Point, visited: &mut HashSet<Point>) -> Point {
+        while !tail.touches(head) {
+            let delta_x = head.x - tail.x;
+            let delta_y = head.y - tail.y;
+            match self {
+                FollowRule::Diagonal => {
+                    tail.x += delta_x.signum();
+                    tail.y += delta_y.signum();
+                },
+                FollowRule::Orthogonal => {
+                    if delta_x.abs() >= delta_y.abs() {
+                        tail.x += delta_x.signum();
+                    } else {
+                        tail.y += delta_y.signum();
+                    }
+                },
+            }
+            visited.insert(tail);
+        }
+        tail
+    }
+}
+
 #[derive(Debug)]
 struct State {
     points: Vec<Point>,
-    tail_points: HashSet<Point>
+    /// positions visited by each knot, including the starting one
+    visited: Vec<HashSet<Point>>,
+    rule: FollowRule,
 }
 
 impl State {
-    fn new(size: usize) -> Self {
+    fn new(size: usize, rule: FollowRule) -> Self {
         Self {
             points: vec![Point::default(); size],
-            tail_points: HashSet::new(),
+            visited: vec![HashSet::from([Point::default()]); size],
+            rule,
         }
     }
 
     fn apply_move(&mut self, m: Move) {
         for _ in 0..m.len {
-            match m.direction {
-                Direction::Up => self.points[0].y += 1,
-                Direction::Down => self.points[0].y -= 1,
-                Direction::Right => self.points[0].x += 1,
-                Direction::Left => self.points[0].x -= 1,
-            }
-
-            for i in 0..(self.points.len() - 1) {
-                let head = self.points[i];
-                let mut tail = &mut self.points[i + 1];
+            self.points[0].x += m.dx;
+            self.points[0].y += m.dy;
+            self.visited[0].insert(self.points[0]);
 
-                let delta_x = tail.x - head.x;
-                let delta_y = tail.y - head.y;
-
-                if delta_x.abs() + delta_y.abs() > 2 {
-                    if delta_x.abs() == 1 {
-                        tail.x -= delta_x;
-                    }
-                    if delta_y.abs() == 1 {
-                        tail.y -= delta_y;
-                    }
-                }
-
-                if delta_y > 1 {
-                    tail.y -= 1;
-                } else if delta_y < -1 {
-                    tail.y += 1;
-                }
-                if delta_x > 1 {
-                    tail.x -= 1;
-                } else if delta_x < -1 {
-                    tail.x += 1;
-                }
+            for i in 1..self.points.len() {
+                self.points[i] = self.rule.follow(self.points[i - 1], self.points[i], &mut self.visited[i]);
             }
-            self.tail_points.insert(self.points[self.points.len() - 1]);
         }
     }
 
     fn touched_points(&self) -> usize {
-        self.tail_points.len()
+        self.visited.last().map_or(0, HashSet::len)
     }
 
+    /// draws the rope and the positions visited by the tail, as in the puzzle
+    /// description: knots are `H` and their index (`T` for a rope of 2 knots),
+    /// `s` is the start and `#` the tail trail
+    fn render(&self) -> String {
+        let tail_trail = self.visited.last().unwrap();
+        let all_points = || tail_trail.iter().chain(self.points.iter());
+        let min_x = all_points().map(|p| p.x).min().unwrap();
+        let max_x = all_points().map(|p| p.x).max().unwrap();
+        let min_y = all_points().map(|p| p.y).min().unwrap();
+        let max_y = all_points().map(|p| p.y).max().unwrap();
+
+        let mut lines = Vec::new();
+        for y in (min_y..=max_y).rev() {
+            let mut line = String::new();
+            for x in min_x..=max_x {
+                let point = Point { x, y };
+                let c = match self.points.iter().position(|p| *p == point) {
+                    Some(0) => 'H',
+                    Some(_) if self.points.len() == 2 => 'T',
+                    Some(i) => char::from_digit(i as u32, 36).unwrap_or('*'),
+                    None if point == Point::default() => 's',
+                    None if tail_trail.contains(&point) => '#',
+                    None => '.',
+                };
+                line.push(c);
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct AoCDay9 {
-    moves: Vec<Move>
+    moves: Vec<Move>,
+    /// number of knots of the rope in part 1 and part 2
+    knots: [usize; 2],
+    rule: FollowRule,
+    /// number of moves after which the rope of part 2 is drawn in the report
+    render_after: Option<usize>,
+}
+
+impl Default for AoCDay9 {
+    fn default() -> Self {
+        Self {
+            moves: Vec::new(),
+            knots: [2, 10],
+            rule: FollowRule::default(),
+            render_after: None,
+        }
+    }
+}
+
+impl AoCDay9 {
+    fn simulate(&self, knots: usize, moves: usize) -> State {
+        let mut state = State::new(knots, self.rule);
+        for m in self.moves.iter().take(moves) {
+            state.apply_move(*m);
+        }
+        state
+    }
 }
 
 impl AoCProblem for AoCDay9 {
-    fn parse_line(&mut self, line: String) {
-        let parts: Vec<&str> = line.split(' ').collect();
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        let (direction, len) = line
+            .split_once(' ')
+            .ok_or_else(|| format!("invalid move: {}", line))?;
+        let (dx, dy) = parse_direction(direction)?;
         self.moves.push(Move {
-            direction: Direction::from(parts[0]),
-            len: parts[1].parse().unwrap(),
+            dx,
+            dy,
+            len: len.parse().map_err(|_| format!("invalid move length: {}", len))?,
         });
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        let mut state = State::new(2);
-        for m in &self.moves {
-            state.apply_move(*m);
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "knots1" => self.knots[0] = parse_positive(key, value)?,
+            "knots2" => self.knots[1] = parse_positive(key, value)?,
+            "follow" => {
+                self.rule = match value {
+                    "diagonal" => FollowRule::Diagonal,
+                    "orthogonal" => FollowRule::Orthogonal,
+                    _ => return Err(format!("unknown follow rule: {} (expected diagonal or orthogonal)", value)),
+                };
+            },
+            "render_after" => {
+                self.render_after = Some(value.parse().map_err(|_| format!("invalid number of moves: {}", value))?);
+            },
+            _ => return Err(format!("unknown option: {}", key)),
         }
-        state.touched_points().to_string()
+        Ok(())
     }
 
-    fn solve_part2(&self) -> String {
-        let mut state = State::new(10);
-        for m in &self.moves {
-            state.apply_move(*m);
+    fn solve_part1(&self) -> AoCResult<String> {
+        let state = self.simulate(self.knots[0], self.moves.len());
+        Ok(state.touched_points().to_string())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        let state = self.simulate(self.knots[1], self.moves.len());
+        Ok(state.touched_points().to_string())
+    }
+
+    fn report(&self) -> AoCResult<String> {
+        let moves = self.render_after.unwrap_or(self.moves.len()).min(self.moves.len());
+        let state = self.simulate(self.knots[1], moves);
+        let mut lines = vec![format!("rope of {} knots after {} of {} moves", self.knots[1], moves, self.moves.len())];
+        for (i, visited) in state.visited.iter().enumerate() {
+            lines.push(format!("  knot {}: {} positions visited", i, visited.len()));
+        }
+        lines.push(String::new());
+        lines.push(state.render());
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FollowRule, Move, Point, State};
+    use crate::random::Rng;
+    use std::collections::HashSet;
+
+    const SEEDS: u64 = 200;
+    const MOVES: usize = 100;
+
+    /// random moves of length 1 to 5, including diagonal ones
+    fn random_moves(rng: &mut Rng) -> Vec<Move> {
+        (0..MOVES)
+            .map(|_| {
+                let (dx, dy) = loop {
+                    let (dx, dy) = (rng.range(0, 2) as i32 - 1, rng.range(0, 2) as i32 - 1);
+                    if (dx, dy) != (0, 0) {
+                        break (dx, dy);
+                    }
+                };
+                Move { dx, dy, len: rng.range(1, 5) as u32 }
+            })
+            .collect()
+    }
+
+    /// single steps of the head, so that invariants can be checked after each one
+    fn steps(moves: &[Move]) -> impl Iterator<Item = Move> + '_ {
+        moves.iter().flat_map(|m| (0..m.len).map(|_| Move { len: 1, ..*m }))
+    }
+
+    /// straightforward implementation of the puzzle rule
+    fn reference_follow(head: Point, tail: Point) -> Point {
+        let (delta_x, delta_y) = (head.x - tail.x, head.y - tail.y);
+        if delta_x.abs() > 1 || delta_y.abs() > 1 {
+            Point { x: tail.x + delta_x.signum(), y: tail.y + delta_y.signum() }
+        } else {
+            tail
+        }
+    }
+
+    #[test]
+    fn test_knots_always_touch() {
+        for rule in [FollowRule::Diagonal, FollowRule::Orthogonal] {
+            for seed in 0..SEEDS {
+                let moves = random_moves(&mut Rng::new(seed));
+                let mut state = State::new(10, rule);
+                for step in steps(&moves) {
+                    state.apply_move(step);
+                    for pair in state.points.windows(2) {
+                        assert!(pair[0].touches(pair[1]), "seed {}: {:?} and {:?} not touching", seed, pair[0], pair[1]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_orthogonal_visits_every_step() {
+        let mut state = State::new(2, FollowRule::Orthogonal);
+        let up_right = Move { dx: 1, dy: 1, len: 2 };
+        state.apply_move(up_right);
+        assert_eq!(state.points[1], Point { x: 1, y: 1 });
+        let expected = [Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 1, y: 1 }];
+        assert_eq!(state.visited[1], HashSet::from(expected));
+    }
+
+    #[test]
+    fn test_visited_count_monotonic() {
+        for seed in 0..SEEDS {
+            let moves = random_moves(&mut Rng::new(seed));
+            let mut state = State::new(10, FollowRule::Diagonal);
+            let mut previous = state.touched_points();
+            for step in steps(&moves) {
+                state.apply_move(step);
+                let current = state.touched_points();
+                assert!(current >= previous, "seed {}: visited count went from {} to {}", seed, previous, current);
+                assert!(current <= previous + 1, "seed {}: tail visited more than one position in a step", seed);
+                previous = current;
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_rope_is_prefix_of_long_rope() {
+        for seed in 0..SEEDS {
+            let moves = random_moves(&mut Rng::new(seed));
+            let mut short = State::new(2, FollowRule::Diagonal);
+            let mut long = State::new(10, FollowRule::Diagonal);
+            for step in steps(&moves) {
+                short.apply_move(step);
+                long.apply_move(step);
+                assert_eq!(short.points[..], long.points[..2], "seed {}", seed);
+            }
+            assert_eq!(short.visited[1], long.visited[1], "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        for seed in 0..SEEDS {
+            let moves = random_moves(&mut Rng::new(seed));
+            let mut state = State::new(10, FollowRule::Diagonal);
+            let mut knots = vec![Point::default(); 10];
+            for step in steps(&moves) {
+                state.apply_move(step);
+                knots[0].x += step.dx;
+                knots[0].y += step.dy;
+                for i in 1..knots.len() {
+                    knots[i] = reference_follow(knots[i - 1], knots[i]);
+                }
+                assert_eq!(state.points, knots, "seed {}", seed);
+            }
         }
-        state.touched_points().to_string()
     }
 }
@@ -1,100 +1,264 @@
-use crate::problem::AoCProblem;
+use crate::cpu::{self, Breakpoint, Cpu, Program, Step};
+use crate::ocr::{self, Image};
+use crate::problem::{parse_positive, AoCProblem, AoCResult};
 
 const CHECK_AT_CYCLES: [usize; 6] = [20, 60, 100, 140, 180, 220];
 const DISPLAY_WIDTH: usize = 40;
 const DISPLAY_HEIGHT: usize = 6;
+const SPRITE_WIDTH: usize = 3;
 
-#[derive(Debug, Clone, Copy)]
-enum Instruction {
-    Noop,
-    Addx(i64),
+/// cycles where the signal strength is measured
+#[derive(Debug, Clone, PartialEq)]
+enum Samples {
+    At(Vec<usize>),
+    /// every `step` cycles starting from `start`, until the end of the program
+    Every { start: usize, step: usize },
 }
 
-impl From<&str> for Instruction {
-    fn from(s: &str) -> Self {
-        let parts: Vec<&str> = s.split(' ').collect();
-        match parts[0] {
-            "noop" => Instruction::Noop,
-            "addx" => Instruction::Addx(parts[1].parse().unwrap()).to_owned(),
-            _ => panic!("invalid input")
+#[derive(Debug)]
+pub struct AoCDay10 {
+    program: Program,
+    /// where the execution stops to show the registers in the report
+    breakpoints: Vec<Breakpoint>,
+    samples: Samples,
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+}
+
+impl Default for AoCDay10 {
+    fn default() -> Self {
+        Self {
+            program: Program::new(),
+            breakpoints: Vec::new(),
+            samples: Samples::At(CHECK_AT_CYCLES.to_vec()),
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            sprite_width: SPRITE_WIDTH,
         }
     }
 }
 
-#[derive(Debug, Default)]
-pub struct AoCDay10 {
-    instructions: Vec<Instruction>
+/// parses the sample cycles, either a list like `20,60,100` or `START+STEP` for
+/// every STEP cycles starting from START
+fn parse_samples(value: &str) -> AoCResult<Samples> {
+    if let Some((start, step)) = value.split_once('+') {
+        return Ok(Samples::Every { start: parse_positive("start", start)?, step: parse_positive("step", step)? });
+    }
+    let cycles = value.split(',').map(|cycle| parse_positive("cycle", cycle)).collect::<AoCResult<_>>()?;
+    Ok(Samples::At(cycles))
 }
 
-#[derive(Debug, Clone, Copy)]
-struct CpuRegisters {
-    x: i64
+/// parses a breakpoint in the form `cycle:N` or `instruction:N`
+fn parse_breakpoint(value: &str) -> AoCResult<Breakpoint> {
+    let (kind, n) = value
+        .split_once(':')
+        .ok_or_else(|| format!("invalid breakpoint: {} (expected cycle:N or instruction:N)", value))?;
+    let n = n.parse().map_err(|_| format!("invalid breakpoint: {}", value))?;
+    match kind {
+        "cycle" => Ok(Breakpoint::Cycle(n)),
+        "instruction" => Ok(Breakpoint::Instruction(n)),
+        _ => Err(format!("invalid breakpoint: {} (expected cycle:N or instruction:N)", value)),
+    }
 }
 
-#[derive(Debug)]
-struct Cpu {
-    registers: CpuRegisters,
-    history: Vec<CpuRegisters>,
-}
+impl AoCDay10 {
+    /// state of every cycle of the program
+    fn steps(&self) -> Vec<Step> {
+        Cpu::new(&self.program).collect()
+    }
 
-impl Cpu {
-    fn new() -> Self {
-        Self {
-            registers: CpuRegisters {
-                x: 1
-            },
-            history: Vec::new()
+    /// pixels lit on the CRT, one for each cycle, where the sprite is under the
+    /// beam, and the number of pixels drawn before the program ends
+    fn crt(&self) -> (Image, usize) {
+        let steps = self.steps();
+        // the sprite covers `sprite_width` pixels centered on the register
+        let left = (self.sprite_width as i64 - 1) / 2;
+        let right = self.sprite_width as i64 - 1 - left;
+        let mut image = vec![vec![false; self.width]; self.height];
+        for (y, row) in image.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                if let Some(step) = steps.get(y * self.width + x) {
+                    let current_x = step.during.x();
+                    *pixel = current_x - left <= x as i64 && x as i64 <= current_x + right;
+                }
+            }
+        }
+        (image, steps.len().min(self.width * self.height))
+    }
+
+    /// flags the answer when the program ends before drawing the whole screen
+    fn flag_partial(&self, answer: String, drawn: usize) -> String {
+        match self.width * self.height - drawn {
+            0 => answer,
+            n => format!("{} (the program ends at cycle {}, {} pixels not drawn)", answer, drawn, n),
         }
     }
+}
+
+/// parses a picture of the display, where `#` is a lit pixel; missing pixels
+/// at the end of a line are not lit
+pub fn parse_image(text: &str) -> AoCResult<Image> {
+    let mut image: Image = text.lines().map(|line| line.chars().map(|c| c == '#').collect()).collect();
+    if image.len() != DISPLAY_HEIGHT || image.iter().any(|row| row.len() > DISPLAY_WIDTH) {
+        return Err(format!("the image must be {} lines of {} pixels", DISPLAY_HEIGHT, DISPLAY_WIDTH));
+    }
+    for row in image.iter_mut() {
+        row.resize(DISPLAY_WIDTH, false);
+    }
+    Ok(image)
+}
+
+/// Generates a program drawing an image on the CRT. The sprite moves only at
+/// the end of an `addx`, so the pixels are drawn in pairs, each with a sprite
+/// position that lights exactly the lit ones of the pair: since the display
+/// width is even, a pair never spans two rows.
+pub fn compile_image(image: &Image) -> AoCResult<Program> {
+    let pixels: Vec<bool> = image.iter().flatten().copied().collect();
+    let lit = |x: i64, cycle: usize| {
+        let column = (cycle % DISPLAY_WIDTH) as i64;
+        (column - 1..=column + 1).contains(&x) == pixels[cycle]
+    };
 
-    fn execute(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Noop => {
-                self.history.push(self.registers);
-            },
-            Instruction::Addx(val) => {
-                self.history.push(self.registers);
-                self.history.push(self.registers);
-                self.registers.x += val;
+    let mut positions = Vec::new();
+    let mut x = 1;
+    for cycle in (0..pixels.len()).step_by(2) {
+        let column = (cycle % DISPLAY_WIDTH) as i64;
+        if !(lit(x, cycle) && lit(x, cycle + 1)) {
+            if cycle == 0 {
+                return Err("the first two pixels must be lit, the sprite starts at x=1".into());
             }
+            x = match (pixels[cycle], pixels[cycle + 1]) {
+                (true, true) => column,
+                (true, false) => column - 1,
+                (false, true) => column + 2,
+                (false, false) => column + 3,
+            };
         }
+        positions.push(x);
     }
+
+    let mut program = Program::new();
+    for pair in positions.windows(2) {
+        if pair[0] == pair[1] {
+            program.push(Box::new(cpu::Noop));
+            program.push(Box::new(cpu::Noop));
+        } else {
+            program.push(Box::new(cpu::Add { register: 0, value: pair[1] - pair[0] }));
+        }
+    }
+    // the last pair has nothing left to move the sprite for
+    program.push(Box::new(cpu::Noop));
+    program.push(Box::new(cpu::Noop));
+    Ok(program)
+}
+
+fn render(image: &Image) -> String {
+    let lines: Vec<String> = image
+        .iter()
+        .map(|row| row.iter().map(|lit| if *lit { '#' } else { ' ' }).collect())
+        .collect();
+    lines.join("\n")
 }
 
 impl AoCProblem for AoCDay10 {
-    fn parse_line(&mut self, line: String) {
-        self.instructions.push(Instruction::from(line.as_str()));
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        if let Some(instruction) = cpu::parse_line(&line)? {
+            self.program.push(instruction);
+        }
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        let mut cpu = Cpu::new();
-        for i in &self.instructions {
-            cpu.execute(i);
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "break" => self.breakpoints.push(parse_breakpoint(value)?),
+            "samples" => self.samples = parse_samples(value)?,
+            "width" => self.width = parse_positive(key, value)?,
+            "height" => self.height = parse_positive(key, value)?,
+            "sprite_width" => self.sprite_width = parse_positive(key, value)?,
+            _ => return Err(format!("unknown option: {}", key)),
         }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        let steps = self.steps();
+        let cycles = match &self.samples {
+            Samples::At(cycles) => cycles.clone(),
+            Samples::Every { start, step } => (*start..=steps.len()).step_by(*step).collect(),
+        };
         let mut result = 0;
-        for c in CHECK_AT_CYCLES {
-            result += cpu.history[c - 1].x * (c as i64);
+        for c in cycles {
+            let step = steps
+                .get(c - 1)
+                .ok_or_else(|| format!("the program ends at cycle {}, before cycle {}", steps.len(), c))?;
+            result += step.during.x() * (c as i64);
+        }
+        Ok(result.to_string())
+    }
+
+    fn solve_part2(&self) -> AoCResult<String> {
+        let (image, drawn) = self.crt();
+        match ocr::recognize(&image) {
+            Ok(letters) => Ok(self.flag_partial(letters, drawn)),
+            Err(e) => Err(format!("{}, the image is shown with --report", self.flag_partial(e, drawn))),
         }
-        result.to_string()
     }
 
-    fn solve_part2(&self) -> String {
-        let mut cpu = Cpu::new();
-        for i in &self.instructions {
-            cpu.execute(i);
+    fn report(&self) -> AoCResult<String> {
+        let (image, drawn) = self.crt();
+        let mut lines = vec![self.flag_partial("CRT:".into(), drawn), render(&image)];
+        if self.breakpoints.is_empty() {
+            return Ok(lines.join("\n"));
         }
-        let mut result = String::from("\n");
-        for y in 0..DISPLAY_HEIGHT {
-            for x in 0..DISPLAY_WIDTH {
-                let current_x = cpu.history[y * DISPLAY_WIDTH + x].x;
-                if current_x - 1 <= x as i64 && x as i64 <= current_x + 1 {
-                    result.push('#');
-                } else {
-                    result.push(' ');
+
+        lines.push(String::new());
+        let mut cpu = Cpu::new(&self.program);
+        for breakpoint in &self.breakpoints {
+            cpu.add_breakpoint(*breakpoint);
+        }
+        while let Some(step) = cpu.run() {
+            let line = format!(
+                "break at cycle {}, instruction {} ({}): {}",
+                step.cycle, step.pc, self.program[step.pc], step.during
+            );
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push(format!("program ended after {} instructions", self.program.len()));
+        Ok(lines.join("\n"))
+    }
+
+    fn trace(&self) -> AoCResult<String> {
+        let mut lines = vec!["cycle   pc    instruction   during                      after".to_string()];
+        for step in self.steps() {
+            let instruction = self.program[step.pc].to_string();
+            let line = format!("{:<7} {:<5} {:<13} {}  {}", step.cycle, step.pc, instruction, step.during, step.after);
+            lines.push(line.trim_end().to_string());
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile_image, AoCDay10, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+    use crate::random::Rng;
+
+    #[test]
+    fn test_compiled_image_is_drawn() {
+        for seed in 0..100 {
+            let mut rng = Rng::new(seed);
+            let mut image = vec![vec![false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+            for row in image.iter_mut() {
+                for pixel in row.iter_mut() {
+                    *pixel = rng.bool();
                 }
             }
-            result.push('\n');
+            image[0][0] = true;
+            image[0][1] = true;
+
+            let problem = AoCDay10 { program: compile_image(&image).unwrap(), ..Default::default() };
+            assert_eq!(problem.crt(), (image, DISPLAY_WIDTH * DISPLAY_HEIGHT), "seed {}", seed);
         }
-        result
     }
 }
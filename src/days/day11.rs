@@ -1,18 +1,251 @@
-use crate::problem::AoCProblem;
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
-enum Operation {
-    Add(u64),
-    Mul(u64),
-    Square,
+use crate::bigint::BigUint;
+use crate::problem::{AoCProblem, AoCResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Add | Operator::Sub => 1,
+            Operator::Mul => 2,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Sub => '-',
+            Operator::Mul => '*',
+        }
+    }
 }
 
-impl Operation {
-    fn execute(&self, value: u64) -> u64 {
+/// the new worry level, computed from the old one
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Old,
+    Constant(u64),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl Expression {
+    fn eval<W: Worry>(&self, old: &W) -> AoCResult<W> {
+        match self {
+            Expression::Old => Ok(old.clone()),
+            Expression::Constant(value) => Ok(old.constant(*value)),
+            Expression::Binary(left, operator, right) => left.eval(old)?.apply(*operator, &right.eval(old)?),
+        }
+    }
+
+    /// writes the expression with only the parentheses needed to keep its meaning
+    fn fmt_with_precedence(&self, f: &mut fmt::Formatter<'_>, min_precedence: u8) -> fmt::Result {
         match self {
-            Operation::Add(v) => value + v,
-            Operation::Mul(v) => value * v,
-            Operation::Square => value * value,
+            Expression::Old => write!(f, "old"),
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Binary(left, operator, right) => {
+                let precedence = operator.precedence();
+                if precedence < min_precedence {
+                    write!(f, "(")?;
+                }
+                left.fmt_with_precedence(f, precedence)?;
+                write!(f, " {} ", operator.symbol())?;
+                // operators are left associative, the right operand needs parentheses with the same precedence
+                right.fmt_with_precedence(f, precedence + 1)?;
+                if precedence < min_precedence {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with_precedence(f, 0)
+    }
+}
+
+/// a worry level, and the arithmetic used to compute the new ones
+trait Worry: Clone + fmt::Debug {
+    /// a constant value with the same arithmetic of this one
+    fn constant(&self, value: u64) -> Self;
+    fn apply(&self, operator: Operator, other: &Self) -> AoCResult<Self>;
+    fn divide(&self, divisor: u64) -> AoCResult<Self>;
+    fn is_multiple_of(&self, divisor: u64) -> bool;
+}
+
+/// Worry level reduced modulo the least common multiple of the divisors of
+/// the tests, which gives the same results of the tests as the real level as
+/// long as it's never divided. Subtractions assume that the real level never
+/// goes below zero, since it can't be known.
+#[derive(Debug, Clone, Copy)]
+struct Modular {
+    value: u128,
+    modulus: u128,
+}
+
+impl Worry for Modular {
+    fn constant(&self, value: u64) -> Self {
+        Self { value: value as u128 % self.modulus, modulus: self.modulus }
+    }
+
+    fn apply(&self, operator: Operator, other: &Self) -> AoCResult<Self> {
+        let (a, b, m) = (self.value, other.value, self.modulus);
+        let result = match operator {
+            Operator::Add => a.checked_add(b),
+            Operator::Sub => a.checked_add(m).map(|v| v - b),
+            Operator::Mul => a.checked_mul(b),
+        };
+        let value = result.ok_or_else(|| {
+            format!("overflow computing {} {} {} modulo {}", a, operator.symbol(), b, m)
+        })?;
+        Ok(Self { value: value % m, modulus: m })
+    }
+
+    fn divide(&self, divisor: u64) -> AoCResult<Self> {
+        // the remainder of a division can't be computed from the remainder of the dividend
+        match divisor {
+            1 => Ok(*self),
+            _ => Err(format!("worry levels modulo {} can't be divided by {}", self.modulus, divisor)),
+        }
+    }
+
+    fn is_multiple_of(&self, divisor: u64) -> bool {
+        self.value.is_multiple_of(divisor as u128)
+    }
+}
+
+/// exact worry level, which can grow without limits
+impl Worry for BigUint {
+    fn constant(&self, value: u64) -> Self {
+        BigUint::from(value)
+    }
+
+    fn apply(&self, operator: Operator, other: &Self) -> AoCResult<Self> {
+        match operator {
+            Operator::Add => Ok(self.add(other)),
+            Operator::Sub => self
+                .checked_sub(other)
+                .ok_or_else(|| format!("negative worry level computing {} - {}", self, other)),
+            Operator::Mul => Ok(self.mul(other)),
+        }
+    }
+
+    fn divide(&self, divisor: u64) -> AoCResult<Self> {
+        Ok(self.div_rem(divisor).0)
+    }
+
+    fn is_multiple_of(&self, divisor: u64) -> bool {
+        self.div_rem(divisor).1 == 0
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Old,
+    Number(u64),
+    Operator(Operator),
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> AoCResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' => {},
+            '+' => tokens.push(Token::Operator(Operator::Add)),
+            '-' => tokens.push(Token::Operator(Operator::Sub)),
+            '*' => tokens.push(Token::Operator(Operator::Mul)),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            '0'..='9' => {
+                let mut number = c.to_string();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                tokens.push(Token::Number(number.parse().map_err(|_| format!("number too large: {}", number))?));
+            },
+            'a'..='z' => {
+                let mut word = c.to_string();
+                while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+                    word.push(letter);
+                }
+                if word != "old" {
+                    return Err(format!("unknown variable: {}", word));
+                }
+                tokens.push(Token::Old);
+            },
+            _ => return Err(format!("unexpected character: {}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser of the expressions, with the usual precedence of
+/// multiplication over addition and subtraction.
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExpressionParser {
+    fn parse(s: &str) -> AoCResult<Expression> {
+        let mut parser = Self { tokens: tokenize(s)?, position: 0 };
+        let expression = parser.expression(1)?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expression),
+            Some(token) => Err(format!("unexpected {:?} in expression: {}", token, s)),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// parses operations of at least the given precedence
+    fn expression(&mut self, min_precedence: u8) -> AoCResult<Expression> {
+        let mut left = self.operand()?;
+        while let Some(Token::Operator(operator)) = self.tokens.get(self.position).cloned() {
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(operator.precedence() + 1)?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> AoCResult<Expression> {
+        match self.next() {
+            Some(Token::Old) => Ok(Expression::Old),
+            Some(Token::Number(value)) => Ok(Expression::Constant(value)),
+            Some(Token::LParen) => {
+                let expression = self.expression(1)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expression),
+                    _ => Err("missing closing parenthesis".into()),
+                }
+            },
+            Some(token) => Err(format!("unexpected {:?}, expected an operand", token)),
+            None => Err("unexpected end of expression".into()),
         }
     }
 }
@@ -25,8 +258,8 @@ struct Test {
 }
 
 impl Test {
-    fn eval(&self, value: u64) -> usize {
-        if value % self.divisible_by == 0 {
+    fn eval<W: Worry>(&self, value: &W) -> usize {
+        if value.is_multiple_of(self.divisible_by) {
             self.if_true
         } else {
             self.if_false
@@ -37,124 +270,454 @@ impl Test {
 #[derive(Debug)]
 struct Monkey {
     starting_items: Vec<u64>,
-    operation: Operation,
+    operation: Expression,
     test: Test,
 }
 
+/// the notes about a monkey, as they are read: any field can still be missing
+#[derive(Debug, Default)]
+struct MonkeyNotes {
+    starting_items: Option<Vec<u64>>,
+    operation: Option<Expression>,
+    divisible_by: Option<u64>,
+    if_true: Option<usize>,
+    if_false: Option<usize>,
+}
+
+impl MonkeyNotes {
+    fn parse_field(&mut self, name: &str, value: &str) -> AoCResult<()> {
+        fn set<T>(field: &mut Option<T>, name: &str, value: T) -> AoCResult<()> {
+            match field.replace(value) {
+                Some(_) => Err(format!("duplicate field: {}", name)),
+                None => Ok(()),
+            }
+        }
+        fn parse_after<T: std::str::FromStr>(value: &str, prefix: &str) -> AoCResult<T> {
+            value
+                .strip_prefix(prefix)
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or_else(|| format!("invalid value: {} (expected {}N)", value, prefix))
+        }
+
+        match name {
+            "Starting items" => {
+                let items = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| item.parse().map_err(|_| format!("invalid item: {}", item)))
+                    .collect::<AoCResult<_>>()?;
+                set(&mut self.starting_items, name, items)
+            },
+            "Operation" => {
+                let expression = value
+                    .strip_prefix("new =")
+                    .ok_or_else(|| format!("invalid operation: {} (expected new = ...)", value))?;
+                set(&mut self.operation, name, ExpressionParser::parse(expression)?)
+            },
+            "Test" => {
+                let divisible_by = parse_after(value, "divisible by ")?;
+                if divisible_by == 0 {
+                    return Err("can't test divisibility by 0".into());
+                }
+                set(&mut self.divisible_by, name, divisible_by)
+            },
+            "If true" => set(&mut self.if_true, name, parse_after(value, "throw to monkey ")?),
+            "If false" => set(&mut self.if_false, name, parse_after(value, "throw to monkey ")?),
+            _ => Err(format!("unknown field: {}", name)),
+        }
+    }
+
+    fn build(&self, id: usize, monkeys: usize) -> AoCResult<Monkey> {
+        fn field<T: Clone>(value: &Option<T>, id: usize, name: &str) -> AoCResult<T> {
+            value.clone().ok_or_else(|| format!("monkey {}: missing field {}", id, name))
+        }
+
+        let test = Test {
+            divisible_by: field(&self.divisible_by, id, "Test")?,
+            if_true: field(&self.if_true, id, "If true")?,
+            if_false: field(&self.if_false, id, "If false")?,
+        };
+        for target in [test.if_true, test.if_false] {
+            if target == id {
+                return Err(format!("monkey {}: throws to itself", id));
+            }
+            if target >= monkeys {
+                return Err(format!("monkey {}: throws to monkey {}, but there are only {}", id, target, monkeys));
+            }
+        }
+        Ok(Monkey {
+            starting_items: field(&self.starting_items, id, "Starting items")?,
+            operation: field(&self.operation, id, "Operation")?,
+            test,
+        })
+    }
+}
+
+/// writes the notes about the monkeys in the format of the puzzle
+struct Notes<'a>(&'a [Monkey]);
+
+impl fmt::Display for Notes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, monkey) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let items: Vec<String> = monkey.starting_items.iter().map(u64::to_string).collect();
+            writeln!(f, "Monkey {}:", i)?;
+            writeln!(f, "  Starting items: {}", items.join(", "))?;
+            writeln!(f, "  Operation: new = {}", monkey.operation)?;
+            writeln!(f, "  Test: divisible by {}", monkey.test.divisible_by)?;
+            writeln!(f, "    If true: throw to monkey {}", monkey.test.if_true)?;
+            writeln!(f, "    If false: throw to monkey {}", monkey.test.if_false)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
-struct State<'a> {
-    monkeys: &'a Vec<Monkey>,
-    worry_levels: Vec<Vec<u64>>,
+struct State<'a, W> {
+    monkeys: &'a [Monkey],
+    worry_levels: Vec<Vec<W>>,
     processed_items: Vec<u64>,
     divide_by: u64,
-    modulus: u64,
 }
 
-impl State<'_> {
-    fn new<'a>(monkeys: &'a Vec<Monkey>, divide_by: u64) -> State<'a> {
-        let mut result: State<'a> = State {
+impl<'a, W: Worry> State<'a, W> {
+    fn new(monkeys: &'a [Monkey], divide_by: u64, worry: impl Fn(u64) -> W) -> Self {
+        Self {
             monkeys,
-            worry_levels: vec![Vec::new(); monkeys.len()],
+            worry_levels: monkeys.iter().map(|m| m.starting_items.iter().map(|i| worry(*i)).collect()).collect(),
             processed_items: vec![0; monkeys.len()],
             divide_by,
-            modulus: 1,
-        };
-        for (i, monkey) in monkeys.iter().enumerate() {
-            result.worry_levels[i].extend(monkey.starting_items.iter());
-            result.modulus *= monkey.test.divisible_by;
         }
-        result
     }
 
-    fn play_monkey(&mut self, i: usize) {
+    fn play_monkey(&mut self, i: usize) -> AoCResult<()> {
         let monkey = &self.monkeys[i];
-        while self.worry_levels[i].len() > 0 {
+        while let Some(level) = self.worry_levels[i].pop() {
             self.processed_items[i] += 1;
-            let mut level = self.worry_levels[i].pop().unwrap();
-            level = monkey.operation.execute(level) % self.modulus;
-            level /= self.divide_by;
-            let throw_at = monkey.test.eval(level);
+            let level = monkey.operation.eval(&level)?.divide(self.divide_by)?;
+            let throw_at = monkey.test.eval(&level);
             self.worry_levels[throw_at].push(level);
         }
+        Ok(())
     }
 
-    fn play_round(&mut self) {
+    fn play_round(&mut self) -> AoCResult<()> {
         for i in 0..self.monkeys.len() {
-            self.play_monkey(i);
+            self.play_monkey(i)?;
         }
+        Ok(())
+    }
+
+    /// number of items inspected by each monkey after each of the given rounds, in increasing order
+    fn play(mut self, checkpoints: &[u64]) -> AoCResult<Vec<Vec<u64>>> {
+        let mut result = Vec::new();
+        let mut round = 0;
+        for checkpoint in checkpoints {
+            while round < *checkpoint {
+                self.play_round()?;
+                round += 1;
+            }
+            result.push(self.processed_items.clone());
+        }
+        Ok(result)
     }
 }
 
-#[derive(Debug, Default)]
+/// The rounds of a single item, simulated on its own since items never
+/// interact: its state at the start of a round is the monkey holding it and
+/// its worry level, and once a state repeats the following rounds repeat too.
+#[derive(Debug)]
+struct ItemHistory {
+    /// items inspected by each monkey before the start of each round
+    inspections: Vec<Vec<u64>>,
+    /// first round of the cycle and its length
+    cycle: Option<(usize, usize)>,
+}
+
+impl ItemHistory {
+    /// simulates an item until its state repeats, or for at most `max_rounds`
+    fn simulate(monkeys: &[Monkey], mut monkey: usize, mut level: Modular, max_rounds: u64) -> AoCResult<Self> {
+        let mut seen = HashMap::new();
+        let mut inspections = vec![vec![0; monkeys.len()]];
+        while ((inspections.len() - 1) as u64) < max_rounds {
+            let round = inspections.len() - 1;
+            if let Some(start) = seen.insert((monkey, level.value), round) {
+                return Ok(Self { inspections, cycle: Some((start, round - start)) });
+            }
+            let mut counts = inspections[round].clone();
+            loop {
+                counts[monkey] += 1;
+                level = monkeys[monkey].operation.eval(&level)?;
+                let target = monkeys[monkey].test.eval(&level);
+                // monkeys play in order, one later in the round still inspects the item in this round
+                let next_round = target < monkey;
+                monkey = target;
+                if next_round {
+                    break;
+                }
+            }
+            inspections.push(counts);
+        }
+        Ok(Self { inspections, cycle: None })
+    }
+
+    /// items inspected by each monkey at the end of the given round
+    fn inspections(&self, round: u64) -> Vec<u64> {
+        let last = self.inspections.len() - 1;
+        match self.cycle {
+            Some((start, length)) if round > last as u64 => {
+                let cycles = (round - start as u64) / length as u64;
+                let offset = ((round - start as u64) % length as u64) as usize;
+                let (first, end) = (&self.inspections[start], &self.inspections[last]);
+                self.inspections[start + offset]
+                    .iter()
+                    .zip(first.iter().zip(end.iter()))
+                    .map(|(count, (first, end))| count + cycles * (end - first))
+                    .collect()
+            },
+            _ => self.inspections[(round as usize).min(last)].clone(),
+        }
+    }
+}
+
+/// how the rounds are simulated when the worry levels are not divided
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// all the items together, one round after the other
+    Rounds,
+    /// each item on its own, skipping the rounds once it's in a cycle
+    Items,
+}
+
+/// product of the number of items inspected by the two most active monkeys
+fn monkey_business(inspections: &[u64]) -> u128 {
+    let mut sorted = inspections.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+    sorted.iter().take(2).map(|count| *count as u128).product()
+}
+
+#[derive(Debug)]
 pub struct AoCDay11 {
-    monkeys: Vec<Monkey>
+    notes: Vec<MonkeyNotes>,
+    /// number of rounds of part 1 and part 2
+    rounds: [u64; 2],
+    mode: Mode,
+    /// rounds after which the report shows the items inspected by each monkey
+    checkpoints: Vec<u64>,
+}
+
+impl Default for AoCDay11 {
+    fn default() -> Self {
+        Self {
+            notes: Vec::new(),
+            rounds: [20, 10_000],
+            mode: Mode::Rounds,
+            checkpoints: [1, 20].into_iter().chain((1..=10).map(|i| i * 1000)).collect(),
+        }
+    }
+}
+
+fn parse_rounds(value: &str) -> AoCResult<u64> {
+    value.parse().map_err(|_| format!("invalid number of rounds: {}", value))
 }
 
 impl AoCDay11 {
-    fn solve(&self, rounds: usize, divide_by: u64) -> String {
-        let mut state = State::new(&self.monkeys, divide_by);
-        for _ in 0..rounds {
-            state.play_round();
+    /// the monkeys described by the notes, checking that they are complete
+    fn monkeys(&self) -> AoCResult<Vec<Monkey>> {
+        if self.notes.is_empty() {
+            return Err("no monkeys in the notes".into());
+        }
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(id, notes)| notes.build(id, self.notes.len()))
+            .collect()
+    }
+
+    /// least common multiple of the divisors of the tests
+    fn modulus(monkeys: &[Monkey]) -> AoCResult<u128> {
+        let mut result: u128 = 1;
+        for monkey in monkeys {
+            let divisor = monkey.test.divisible_by as u128;
+            result = (result / gcd(result, divisor))
+                .checked_mul(divisor)
+                .ok_or("overflow computing the least common multiple of the divisors")?;
+        }
+        Ok(result)
+    }
+
+    /// items inspected by each monkey after each of the given rounds, in
+    /// increasing order; the levels of part 1 are divided, so its rounds are
+    /// always simulated together
+    fn inspections(&self, checkpoints: &[u64], divide_by: u64) -> AoCResult<Vec<Vec<u64>>> {
+        let monkeys = self.monkeys()?;
+        if divide_by != 1 {
+            // the levels are divided, so they must be kept exact
+            return State::new(&monkeys, divide_by, BigUint::from).play(checkpoints);
+        }
+
+        let modulus = Self::modulus(&monkeys)?;
+        let level = |value: u64| Modular { value: value as u128 % modulus, modulus };
+        if self.mode == Mode::Rounds {
+            return State::new(&monkeys, divide_by, level).play(checkpoints);
         }
-        state.processed_items.sort();
-        state.processed_items.reverse();
-        (state.processed_items[0] * state.processed_items[1]).to_string()
+        let max_rounds = checkpoints.iter().max().copied().unwrap_or(0);
+        let mut result = vec![vec![0; monkeys.len()]; checkpoints.len()];
+        for (i, monkey) in monkeys.iter().enumerate() {
+            for item in &monkey.starting_items {
+                let history = ItemHistory::simulate(&monkeys, i, level(*item), max_rounds)?;
+                for (counts, round) in result.iter_mut().zip(checkpoints) {
+                    for (count, item_count) in counts.iter_mut().zip(history.inspections(*round)) {
+                        *count += item_count;
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn solve(&self, rounds: u64, divide_by: u64) -> AoCResult<String> {
+        let inspections = self.inspections(&[rounds], divide_by)?;
+        Ok(monkey_business(&inspections[0]).to_string())
     }
 }
 
 impl AoCProblem for AoCDay11 {
-    fn parse_line(&mut self, line: String) {
-        if line.trim().len() == 0 {
-            return
-        } else if line.starts_with("Monkey") {
-            self.monkeys.push(Monkey {
-                operation: Operation::Add(0),
-                starting_items: Vec::new(),
-                test: Test {
-                    divisible_by: 0,
-                    if_false: 0,
-                    if_true: 0,
-                }
-            })
-        } else {
-            let parts: Vec<&str> = line.split(":").collect();
-            let monkey_len = self.monkeys.len();
-            let monkey = &mut self.monkeys[monkey_len - 1];
-            let last = parts[1].split(" ").last().unwrap().trim();
-            match parts[0].trim() {
-                "Starting items" => {
-                    monkey.starting_items.extend(parts[1].split(",").map(|val| val.trim().parse::<u64>().unwrap()));
-                },
-                "Operation" => {
-                    if parts[1].contains("old * old") {
-                        monkey.operation = Operation::Square;
-                    } else if parts[1].contains("old * ") {
-                        monkey.operation = Operation::Mul(last.parse().unwrap());
-                    } else if parts[1].contains("old + ") {
-                        monkey.operation = Operation::Add(last.parse().unwrap());
-                    } else {
-                        panic!("invalid input");
-                    }
-                },
-                "Test" => {
-                    monkey.test.divisible_by = last.parse().unwrap();
-                },
-                "If true" => {
-                    monkey.test.if_true = last.parse().unwrap();
-                },
-                "If false" => {
-                    monkey.test.if_false = last.parse().unwrap();
-                },
-                _ => panic!("invalid input"),
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(header) = line.strip_prefix("Monkey ") {
+            let id: usize = header
+                .strip_suffix(':')
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| format!("invalid monkey header: {}", line))?;
+            if id != self.notes.len() {
+                return Err(format!("expected monkey {}, found monkey {}", self.notes.len(), id));
             }
+            self.notes.push(MonkeyNotes::default());
+            return Ok(());
         }
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("invalid line: {}", line))?;
+        let monkey = self.notes.last_mut().ok_or("field before the first monkey")?;
+        monkey.parse_field(name.trim(), value.trim())
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "rounds1" => self.rounds[0] = parse_rounds(value)?,
+            "rounds2" => self.rounds[1] = parse_rounds(value)?,
+            "mode" => {
+                self.mode = match value {
+                    "rounds" => Mode::Rounds,
+                    "items" => Mode::Items,
+                    _ => return Err(format!("unknown mode: {} (expected rounds or items)", value)),
+                };
+            },
+            "checkpoints" => {
+                let mut checkpoints = value.split(',').map(parse_rounds).collect::<AoCResult<Vec<_>>>()?;
+                checkpoints.sort();
+                self.checkpoints = checkpoints;
+            },
+            _ => return Err(format!("unknown option: {}", key)),
+        }
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        self.solve(self.rounds[0], 3)
     }
 
-    fn solve_part1(&self) -> String {
-        self.solve(20, 3)
+    fn solve_part2(&self) -> AoCResult<String> {
+        self.solve(self.rounds[1], 1)
     }
 
-    fn solve_part2(&self) -> String {
-        self.solve(10_000, 1)
+    fn report(&self) -> AoCResult<String> {
+        let monkeys = self.monkeys()?;
+        let mut lines = vec![Notes(&monkeys).to_string()];
+        let inspections = self.inspections(&self.checkpoints, 1)?;
+        for (round, counts) in self.checkpoints.iter().zip(inspections) {
+            lines.push(format!("== After round {} ==", round));
+            for (i, count) in counts.iter().enumerate() {
+                lines.push(format!("Monkey {} inspected items {} times.", i, count));
+            }
+            lines.push(String::new());
+        }
+        Ok(lines.join("\n").trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AoCDay11, ExpressionParser, Mode};
+    use crate::bigint::BigUint;
+    use crate::problem::AoCProblem;
+
+    const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1";
+
+    #[test]
+    fn test_expressions() {
+        let cases = [
+            ("old * old", 7, "old * old", 49),
+            ("old+3", 7, "old + 3", 10),
+            ("(old + 1) * (old - 2)", 7, "(old + 1) * (old - 2)", 40),
+            ("old - (old - 3)", 7, "old - (old - 3)", 3),
+            ("(old - 1) - 2", 7, "old - 1 - 2", 4),
+            ("2 * old + 1", 7, "2 * old + 1", 15),
+        ];
+        for (source, old, formatted, value) in cases {
+            let expression = ExpressionParser::parse(source).unwrap();
+            assert_eq!(expression.to_string(), formatted);
+            let result = expression.eval(&BigUint::from(old)).unwrap();
+            assert_eq!(result, BigUint::from(value), "{}", source);
+            assert_eq!(ExpressionParser::parse(formatted), Ok(expression));
+        }
+        for invalid in ["", "old +", "(old", "old)", "new * 2", "old ^ 2"] {
+            assert!(ExpressionParser::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_items_mode_matches_rounds_mode() {
+        let mut problem = AoCDay11::default();
+        for line in EXAMPLE.lines() {
+            problem.parse_line(line.to_string()).unwrap();
+        }
+        let checkpoints: Vec<u64> = (0..2000).step_by(37).collect();
+        let rounds = problem.inspections(&checkpoints, 1).unwrap();
+        problem.mode = Mode::Items;
+        assert_eq!(problem.inspections(&checkpoints, 1).unwrap(), rounds);
     }
 }
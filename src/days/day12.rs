@@ -1,16 +1,40 @@
-use crate::problem::AoCProblem;
+use crate::problem::{AoCProblem, AoCResult};
 use std::collections::BinaryHeap;
 use std::collections::HashMap;
 
+type Point = (i32, i32);
+
 #[derive(Debug, Default)]
 pub struct AoCDay12 {
     elevation: Vec<Vec<u32>>,
-    start: (i32, i32),
-    end: (i32, i32),
+    start: Option<Point>,
+    end: Option<Point>,
+}
+
+/// shortest paths from every point to the end
+#[derive(Debug)]
+struct Paths {
+    end: Point,
+    distance: HashMap<Point, u32>,
+    /// next step on the shortest path to the end
+    next: HashMap<Point, Point>,
+}
+
+impl Paths {
+    /// the points of the shortest path from `start` to the end, both included
+    fn path(&self, start: Point) -> Option<Vec<Point>> {
+        let mut path = vec![start];
+        let mut current = start;
+        while current != self.end {
+            current = *self.next.get(&current)?;
+            path.push(current);
+        }
+        Some(path)
+    }
 }
 
 impl AoCDay12 {
-    fn neighbors(&self, v: (i32, i32)) -> Vec<(i32, i32)> {
+    fn neighbors(&self, v: Point) -> Vec<Point> {
         let mut neighbours = Vec::new();
 
         let max_x = self.elevation[0].len() as i32 - 1;
@@ -37,12 +61,14 @@ impl AoCDay12 {
         neighbours
     }
 
-    fn shortest_path(&self) -> HashMap<(i32, i32), u32> {
-        let mut q: BinaryHeap<(i32, (i32, i32))> = BinaryHeap::new();
-        let mut d: HashMap<(i32, i32), u32> = HashMap::new();
+    fn shortest_path(&self) -> AoCResult<Paths> {
+        let end = self.end.ok_or("missing end position E")?;
+        let mut q: BinaryHeap<(i32, Point)> = BinaryHeap::new();
+        let mut d: HashMap<Point, u32> = HashMap::new();
+        let mut next: HashMap<Point, Point> = HashMap::new();
 
-        q.push((0, self.end));
-        d.insert(self.end,  0);
+        q.push((0, end));
+        d.insert(end, 0);
 
         while let Some((cost_, v)) = q.pop() {
             let cost = -cost_ as u32;
@@ -54,50 +80,113 @@ impl AoCDay12 {
                 let c = cost + 1;
                 if c < *d.get(&u).unwrap_or(&u32::MAX) {
                     d.insert(u, c);
+                    next.insert(u, v);
                     q.push((-(c as i32), u));
                 }
             }
         }
-        d
+        Ok(Paths { end, distance: d, next })
+    }
+
+    /// the lowest point closest to the end, first in reading order on ties
+    fn best_trailhead(&self, paths: &Paths) -> AoCResult<(Point, u32)> {
+        let mut best: Option<(Point, u32)> = None;
+        for (y, e) in self.elevation.iter().enumerate() {
+            for (x, e) in e.iter().enumerate() {
+                let point = (x as i32, y as i32);
+                if let (0, Some(distance)) = (*e, paths.distance.get(&point)) {
+                    if best.is_none_or(|(_, best_distance)| *distance < best_distance) {
+                        best = Some((point, *distance));
+                    }
+                }
+            }
+        }
+        best.ok_or_else(|| "no square of elevation a can reach E".into())
+    }
+
+    /// draws the path on the map, with arrows like in the puzzle description
+    fn render(&self, path: &[Point]) -> String {
+        let mut map: Vec<Vec<char>> = self.elevation.iter().map(|row| vec!['.'; row.len()]).collect();
+        for step in path.windows(2) {
+            let ((x, y), (next_x, next_y)) = (step[0], step[1]);
+            map[y as usize][x as usize] = match (next_x - x, next_y - y) {
+                (1, _) => '>',
+                (-1, _) => '<',
+                (_, 1) => 'v',
+                _ => '^',
+            };
+        }
+        if let Some((x, y)) = path.last() {
+            map[*y as usize][*x as usize] = 'E';
+        }
+        let lines: Vec<String> = map.into_iter().map(|row| row.into_iter().collect()).collect();
+        lines.join("\n")
     }
 }
 
 impl AoCProblem for AoCDay12 {
-    fn parse_line(&mut self, line: String) {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
         let mut row = Vec::new();
+        let y = self.elevation.len() as i32;
         for (i, ch) in line.chars().enumerate() {
             match ch {
                 'S' => {
-                    self.start = (i as i32, self.elevation.len() as i32);
+                    if self.start.replace((i as i32, y)).is_some() {
+                        return Err("more than one start position S".into());
+                    }
                     row.push(0);
                 },
                 'E' => {
-                    self.end = (i as i32, self.elevation.len() as i32);
+                    if self.end.replace((i as i32, y)).is_some() {
+                        return Err("more than one end position E".into());
+                    }
                     row.push('z' as u32 - 'a' as u32);
                 },
                 'a'..='z' => {
                     row.push(ch as u32 - 'a' as u32);
                 },
-                _ => panic!("invalid input"),
+                _ => return Err(format!("invalid elevation: {}", ch)),
+            }
+        }
+        if let Some(first) = self.elevation.first() {
+            if first.len() != row.len() {
+                return Err(format!("expected {} squares in each row, got {}", first.len(), row.len()));
             }
         }
         self.elevation.push(row);
+        Ok(())
+    }
+
+    fn solve_part1(&self) -> AoCResult<String> {
+        let start = self.start.ok_or("missing start position S")?;
+        let paths = self.shortest_path()?;
+        match paths.distance.get(&start) {
+            Some(distance) => Ok(distance.to_string()),
+            None => Err("E can't be reached from S".into()),
+        }
     }
 
-    fn solve_part1(&self) -> String {
-        self.shortest_path().get(&self.start).unwrap().to_string()
+    fn solve_part2(&self) -> AoCResult<String> {
+        let (_, distance) = self.best_trailhead(&self.shortest_path()?)?;
+        Ok(distance.to_string())
     }
 
-    fn solve_part2(&self) -> String {
-        let d = self.shortest_path();
-        let mut response = u32::MAX;
-        for (y, e) in self.elevation.iter().enumerate() {
-            for (x, e) in e.iter().enumerate() {
-                if e == &0 {
-                    response = response.min(*d.get(&(x as i32, y as i32)).unwrap_or(&u32::MAX));
-                }
-            }
+    fn report(&self) -> AoCResult<String> {
+        let paths = self.shortest_path()?;
+        let mut lines = Vec::new();
+        match self.start.and_then(|start| paths.path(start)) {
+            Some(path) => {
+                lines.push(format!("path from S, {} steps:", path.len() - 1));
+                lines.push(self.render(&path));
+            },
+            None => lines.push("E can't be reached from S".into()),
+        }
+        if let Ok(((x, y), distance)) = self.best_trailhead(&paths) {
+            let path = paths.path((x, y)).ok_or("missing path from the trailhead")?;
+            lines.push(String::new());
+            lines.push(format!("best trailhead at x={}, y={}, {} steps:", x, y, distance));
+            lines.push(self.render(&path));
         }
-        response.to_string()
+        Ok(lines.join("\n"))
     }
 }
@@ -1,128 +1,76 @@
-use std::cmp::Ordering;
+use crate::packet::{self, Item};
+use crate::problem::{parse_bool, parse_positive, AoCProblem, AoCResult};
+use serde_json::Value;
 
-use crate::problem::AoCProblem;
+const DIVIDERS: [&str; 2] = ["[[2]]", "[[6]]"];
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum Item {
-    Integer(u32),
-    List(Vec<Item>),
+#[derive(Debug)]
+pub struct AoCDay13 {
+    packets: Vec<Item>,
+    dividers: Vec<Item>,
+    /// pair whose comparison is explained in the report, starting from 1
+    explain: Option<usize>,
+    /// show all the packets in order in the report
+    sorted: bool,
+    /// read the packets with a JSON parser, and show the pairs as a JSON
+    /// document in the report
+    json: bool,
 }
 
-impl ToString for Item {
-    fn to_string(&self) -> String {
-        match self {
-            Item::Integer(val) => val.to_string(),
-            Item::List(values) => {
-                let mut result = String::new();
-                result += "[";
-                for (i, value) in values.iter().enumerate() {
-                    if i > 0 {
-                        result += ",";
-                    }
-                    result += &value.to_string();
-                }
-                result += "]";
-                result
-            }
+impl Default for AoCDay13 {
+    fn default() -> Self {
+        Self {
+            packets: Vec::new(),
+            dividers: DIVIDERS.iter().map(|d| d.parse().unwrap()).collect(),
+            explain: None,
+            sorted: false,
+            json: false,
         }
     }
 }
 
-impl Ord for Item {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Item::List(_), Item::Integer(i2)) => self.cmp(&Item::List(vec![Item::Integer(*i2)])),
-            (Item::Integer(i1), Item::List(_)) => Item::List(vec![Item::Integer(*i1)]).cmp(other),
-            (Item::Integer(i1), Item::Integer(i2)) => i1.cmp(i2),
-            (Item::List(l1), Item::List(l2)) => {
-                for i in 0..l1.len().min(l2.len()) {
-                    match l1[i].cmp(&l2[i]) {
-                        Ordering::Equal => {},
-                        ordering => return ordering,
-                    }
-                }
-                l1.len().cmp(&l2.len())
-            },
+impl AoCDay13 {
+    fn pair(&self, index: usize) -> AoCResult<(&Item, &Item)> {
+        match (self.packets.get(index * 2 - 2), self.packets.get(index * 2 - 1)) {
+            (Some(left), Some(right)) => Ok((left, right)),
+            _ => Err(format!("no pair {}, there are {} pairs", index, self.packets.len() / 2)),
         }
     }
 }
 
-impl PartialOrd for Item {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-#[derive(Debug, Default)]
-pub struct AoCDay13 {
-    packets: Vec<Item>
-}
-
-enum Token {
-    LParen,
-    RParen,
-    Comma,
-    Integer(u32),
-}
-
-fn tokenize(line: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut current_number: Option<u32> = None;
-
-    for c in line.chars() {
-        match c {
-            '0'..='9' => current_number = Some(current_number.unwrap_or(0) * 10 + c.to_digit(10).unwrap()),
-            _ => {
-                if let Some(value) = current_number {
-                    tokens.push(Token::Integer(value));
-                    current_number = None;
-                }
-                match c {
-                    '[' => tokens.push(Token::LParen),
-                    ']' => tokens.push(Token::RParen),
-                    ',' => tokens.push(Token::Comma),
-                    _ => panic!("invalid token: {}", c),
-                }
-
-            }
+impl AoCProblem for AoCDay13 {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
+        if line.trim().is_empty() {
+            return Ok(());
         }
+        let packet = if self.json {
+            let value: Value = serde_json::from_str(&line).map_err(|e| format!("invalid JSON: {}", e))?;
+            Item::try_from(&value)?
+        } else {
+            line.parse()?
+        };
+        self.packets.push(packet);
+        Ok(())
     }
-    tokens
-}
 
-fn parse(line: &str) -> Item {
-    let mut list: Vec<Vec<Item>> = Vec::new();
-    for tok in tokenize(line) {
-        match tok {
-            Token::LParen => {
-                list.push(Vec::new());
-            },
-            Token::RParen => {
-                let value = list.pop().unwrap();
-                if let Some(top) = list.last_mut() {
-                    top.push(Item::List(value));
-                } else {
-                    return Item::List(value);
-                }
-            },
-            Token::Comma => {},
-            Token::Integer(val) => {
-                list.last_mut().unwrap().push(Item::Integer(val));
+    fn set_option(&mut self, key: &str, value: &str) -> AoCResult<()> {
+        match key {
+            "explain" => self.explain = Some(parse_positive(key, value)?),
+            "dividers" => {
+                // packets contain commas, so they are separated by semicolons
+                self.dividers = value
+                    .split(';')
+                    .map(|d| d.parse().map_err(|e| format!("invalid divider {}: {}", d, e)))
+                    .collect::<AoCResult<_>>()?;
             },
+            "sorted" => self.sorted = parse_bool(key, value)?,
+            "json" => self.json = parse_bool(key, value)?,
+            _ => return Err(format!("unknown option: {}", key)),
         }
+        Ok(())
     }
 
-    panic!("invalid input");
-}
-
-impl AoCProblem for AoCDay13 {
-    fn parse_line(&mut self, line: String) {
-        if line.len() > 0 {
-            self.packets.push(parse(&line));
-        }
-    }
-
-    fn solve_part1(&self) -> String {
+    fn solve_part1(&self) -> AoCResult<String> {
         let mut res = 0;
         for i in 0..(self.packets.len() / 2) {
             let a = &self.packets[i * 2];
@@ -131,20 +79,48 @@ impl AoCProblem for AoCDay13 {
                 res += i + 1;
             }
         }
-        res.to_string()
+        Ok(res.to_string())
     }
 
-    fn solve_part2(&self) -> String {
-        let div1 = Item::List(vec![Item::List(vec![Item::Integer(2)])]);
-        let div2 = Item::List(vec![Item::List(vec![Item::Integer(6)])]);
-        let mut packets = self.packets.clone();
-        packets.push(div1.clone());
-        packets.push(div2.clone());
-        packets.sort();
-
-        let div1_i = packets.binary_search(&div1).unwrap() + 1;
-        let div2_i = packets.binary_search(&div2).unwrap() + 1;
+    fn solve_part2(&self) -> AoCResult<String> {
+        let positions = packet::rank(&self.packets, &self.dividers);
+        Ok(positions.iter().product::<usize>().to_string())
+    }
 
-        (div1_i * div2_i).to_string()
+    fn report(&self) -> AoCResult<String> {
+        let mut lines = Vec::new();
+        if let Some(index) = self.explain {
+            let (left, right) = self.pair(index)?;
+            lines.push(format!("== Pair {} ==", index));
+            lines.extend(left.explain_cmp(right).1);
+        }
+        if self.sorted {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let mut all: Vec<(&Item, bool)> = self.packets.iter().map(|p| (p, false)).collect();
+            all.extend(self.dividers.iter().map(|d| (d, true)));
+            // dividers before the packets equal to them, as they are ranked
+            all.sort_by(|(a, a_divider), (b, b_divider)| a.cmp(b).then(b_divider.cmp(a_divider)));
+            for (item, is_divider) in all {
+                lines.push(if is_divider { format!("{} (divider)", item) } else { item.to_string() });
+            }
+        }
+        if self.json {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let pairs = self.packets.chunks(2).map(|pair| Value::Array(pair.iter().map(Value::from).collect()));
+            let document = Value::Array(pairs.collect());
+            lines.push(serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?);
+        }
+        if lines.is_empty() {
+            for index in 1..=self.packets.len() / 2 {
+                let (left, right) = self.pair(index)?;
+                let verdict = if left <= right { "right order" } else { "wrong order" };
+                lines.push(format!("pair {}: {}", index, verdict));
+            }
+        }
+        Ok(lines.join("\n"))
     }
 }
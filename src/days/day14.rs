@@ -1,4 +1,4 @@
-use crate::problem::AoCProblem;
+use crate::problem::{AoCProblem, AoCResult};
 
 const START_POSITION: (usize, usize) = (500, 0);
 const GRID_MAX_SIZE: usize = 1000;
@@ -57,7 +57,7 @@ fn drop_sand(grid: &mut Grid) -> bool {
 }
 
 impl AoCProblem for AoCDay14 {
-    fn parse_line(&mut self, line: String) {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
         let mut points: Vec<(usize, usize)> = Vec::new();
         for part in line.split("->") {
             let coord: Vec<usize> = part.trim().split(",").map(|v| v.parse().unwrap()).collect();
@@ -82,26 +82,27 @@ impl AoCProblem for AoCDay14 {
                 }
             }
         }
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
-        let mut grid = self.grid.clone();
+    fn solve_part1(&self) -> AoCResult<String> {
+        let mut grid = self.grid;
         let mut result = 0;
         while drop_sand(&mut grid) {
             result += 1;
         }
-        result.to_string()
+        Ok(result.to_string())
     }
 
-    fn solve_part2(&self) -> String {
-        let mut grid = self.grid.clone();
-        for i in 0..GRID_MAX_SIZE {
-            grid[self.max_y + 2][i] = Cell::Wall;
+    fn solve_part2(&self) -> AoCResult<String> {
+        let mut grid = self.grid;
+        for cell in grid[self.max_y + 2].iter_mut() {
+            *cell = Cell::Wall;
         }
         let mut result = 0;
         while grid[START_POSITION.1][START_POSITION.0] != Cell::Sand && drop_sand(&mut grid) {
             result += 1;
         }
-        result.to_string()
+        Ok(result.to_string())
     }
 }
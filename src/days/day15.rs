@@ -1,4 +1,4 @@
-use crate::problem::AoCProblem;
+use crate::problem::{AoCProblem, AoCResult};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
@@ -25,7 +25,7 @@ pub struct AoCDay15 {
 }
 
 impl AoCProblem for AoCDay15 {
-    fn parse_line(&mut self, line: String) {
+    fn parse_line(&mut self, line: String) -> AoCResult<()> {
         let parsed = sscanf::sscanf!(line, "Sensor at x={i32}, y={i32}: closest beacon is at x={i32}, y={i32}").unwrap();
         let beacon = Point {
             x: parsed.2,
@@ -43,9 +43,10 @@ impl AoCProblem for AoCDay15 {
         });
         self.y_range = (self.y_range.0.min(beacon.y.min(position.y) - d), self.y_range.1.max(beacon.y.max(position.y) + d));
         self.x_range = (self.x_range.0.min(beacon.x.min(position.x) - d), self.x_range.1.max(beacon.x.max(position.x) + d));
+        Ok(())
     }
 
-    fn solve_part1(&self) -> String {
+    fn solve_part1(&self) -> AoCResult<String> {
         let mut result: u64 = 0;
         let y = if self.sensors.len() < 15 {
             10
@@ -70,10 +71,10 @@ impl AoCProblem for AoCDay15 {
                 result += 1; 
             }
         }
-        result.to_string()
+        Ok(result.to_string())
     }
 
-    fn solve_part2(&self) -> String {
+    fn solve_part2(&self) -> AoCResult<String> {
         let max = if self.sensors.len() < 15 {
             20
         } else {
@@ -110,7 +111,7 @@ impl AoCProblem for AoCDay15 {
                             }
                         }
                         if found {
-                            return ((candidate.x as u64) * 4000000 + (candidate.y as u64)).to_string()
+                            return Ok(((candidate.x as u64) * 4000000 + (candidate.y as u64)).to_string())
                         } 
                     }             
                 }
@@ -0,0 +1,399 @@
+use crate::problem::AoCResult;
+use crate::random::Rng;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// index of a node in the arena of the filesystem
+pub type NodeId = usize;
+
+#[derive(Debug)]
+pub enum NodeKind {
+    File { size: usize },
+    Directory { children: BTreeMap<String, NodeId> },
+}
+
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub kind: NodeKind,
+}
+
+/// In memory filesystem: all the nodes are stored in a vector and refer to each
+/// other by index. A node is always created after its parent, so it has a
+/// greater index.
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                name: "/".into(),
+                parent: None,
+                kind: NodeKind::Directory { children: BTreeMap::new() },
+            }],
+        }
+    }
+}
+
+/// sizes in the style of `du -h`, with a power of 1024 suffix
+pub fn human_size(size: usize) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+impl FileSystem {
+    pub const ROOT: NodeId = 0;
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id].kind, NodeKind::Directory { .. })
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            NodeKind::Directory { children } => children.get(name).copied(),
+            NodeKind::File { .. } => None,
+        }
+    }
+
+    /// resolves a path, absolute or relative to `cwd`, to a directory or file
+    pub fn resolve(&self, cwd: NodeId, path: &str) -> AoCResult<NodeId> {
+        let mut current = if path.starts_with('/') { Self::ROOT } else { cwd };
+        for component in path.split('/') {
+            match component {
+                "" | "." => {},
+                ".." => {
+                    current = self.nodes[current]
+                        .parent
+                        .ok_or_else(|| format!("{}: can't go above the root directory", path))?;
+                },
+                name => {
+                    if !self.is_dir(current) {
+                        return Err(format!("{}: {} is not a directory", path, self.path(current)));
+                    }
+                    current = self
+                        .child(current, name)
+                        .ok_or_else(|| format!("{}: no such file or directory", path))?;
+                },
+            }
+        }
+        Ok(current)
+    }
+
+    fn add(&mut self, dir: NodeId, name: &str, kind: NodeKind) -> AoCResult<NodeId> {
+        let id = self.nodes.len();
+        match &mut self.nodes[dir].kind {
+            NodeKind::Directory { children } => {
+                children.insert(name.into(), id);
+            },
+            NodeKind::File { .. } => return Err(format!("{} is not a directory", self.path(dir))),
+        }
+        self.nodes.push(Node {
+            name: name.into(),
+            parent: Some(dir),
+            kind,
+        });
+        Ok(id)
+    }
+
+    /// creates a directory, or returns the existing one with the same name
+    pub fn mkdir(&mut self, dir: NodeId, name: &str) -> AoCResult<NodeId> {
+        match self.child(dir, name) {
+            Some(id) if self.is_dir(id) => Ok(id),
+            Some(id) => Err(format!("{} already exists and is not a directory", self.path(id))),
+            None => self.add(dir, name, NodeKind::Directory { children: BTreeMap::new() }),
+        }
+    }
+
+    /// creates a file, or returns the existing one with the same name and size
+    pub fn touch(&mut self, dir: NodeId, name: &str, size: usize) -> AoCResult<NodeId> {
+        match self.child(dir, name) {
+            Some(id) => match self.nodes[id].kind {
+                NodeKind::File { size: old_size } if old_size == size => Ok(id),
+                NodeKind::File { size: old_size } => {
+                    Err(format!("{} already exists with size {}, not {}", self.path(id), old_size, size))
+                },
+                NodeKind::Directory { .. } => Err(format!("{} already exists and is a directory", self.path(id))),
+            },
+            None => self.add(dir, name, NodeKind::File { size }),
+        }
+    }
+
+    pub fn path(&self, id: NodeId) -> String {
+        match self.nodes[id].parent {
+            None => "/".into(),
+            Some(Self::ROOT) => format!("/{}", self.nodes[id].name),
+            Some(parent) => format!("{}/{}", self.path(parent), self.nodes[id].name),
+        }
+    }
+
+    /// total size of every node, indexed by node id
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.nodes
+            .iter()
+            .map(|n| match n.kind {
+                NodeKind::File { size } => size,
+                NodeKind::Directory { .. } => 0,
+            })
+            .collect();
+        // children come after their parent, so visiting backwards gives each
+        // directory the complete size of its children before it's added to its parent
+        for id in (1..self.nodes.len()).rev() {
+            let parent = self.nodes[id].parent.unwrap();
+            sizes[parent] += sizes[id];
+        }
+        sizes
+    }
+
+    pub fn directories(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|id| self.is_dir(*id))
+    }
+
+    /// directories with total size in the range `min..=max`, sorted by size
+    pub fn find_directories(&self, min: usize, max: usize) -> Vec<(NodeId, usize)> {
+        let sizes = self.sizes();
+        let mut result: Vec<(NodeId, usize)> = self.directories()
+            .map(|id| (id, sizes[id]))
+            .filter(|(_, size)| (min..=max).contains(size))
+            .collect();
+        result.sort_by_key(|(id, size)| (*size, *id));
+        result
+    }
+
+    fn visit(&self, id: NodeId, pre_order: bool, visitor: &mut impl FnMut(NodeId, usize), depth: usize) {
+        if pre_order {
+            visitor(id, depth);
+        }
+        if let NodeKind::Directory { children } = &self.nodes[id].kind {
+            for child in children.values() {
+                self.visit(*child, pre_order, visitor, depth + 1);
+            }
+        }
+        if !pre_order {
+            visitor(id, depth);
+        }
+    }
+
+    /// total size of every directory, found with a recursive walk of the tree
+    /// like `du` does rather than with `sizes`, to cross check it
+    pub fn walk_directory_sizes(&self) -> Vec<usize> {
+        fn walk(fs: &FileSystem, id: NodeId, result: &mut Vec<usize>) -> usize {
+            match &fs.nodes[id].kind {
+                NodeKind::File { size } => *size,
+                NodeKind::Directory { children } => {
+                    let size = children.values().map(|child| walk(fs, *child, result)).sum();
+                    result.push(size);
+                    size
+                },
+            }
+        }
+
+        let mut result = Vec::new();
+        walk(self, Self::ROOT, &mut result);
+        result
+    }
+
+    /// total size of the directories of at most `max_size`, the day 7 part 1
+    /// answer, from `walk_directory_sizes`
+    pub fn walk_part_1(&self, max_size: usize) -> usize {
+        self.walk_directory_sizes().iter().filter(|size| **size <= max_size).sum()
+    }
+
+    /// size of the smallest directory to delete to have `required_space` free
+    /// on a disk of `total_size`, 0 if there is already enough, the day 7 part
+    /// 2 answer, from `walk_directory_sizes`
+    pub fn walk_part_2(&self, total_size: usize, required_space: usize) -> AoCResult<usize> {
+        let sizes = self.walk_directory_sizes();
+        // the root directory is the last one visited
+        let used = sizes.last().copied().unwrap_or(0);
+        if used > total_size {
+            return Err(format!("{} bytes used on a disk of {}", used, total_size));
+        }
+        let free = total_size - used;
+        if free >= required_space {
+            return Ok(0);
+        }
+        let to_free = required_space - free;
+        sizes
+            .into_iter()
+            .filter(|size| *size >= to_free)
+            .min()
+            .ok_or_else(|| format!("no directory is large enough to free {} bytes", to_free))
+    }
+
+    /// listing in the format of the puzzle description
+    pub fn tree(&self) -> String {
+        let sizes = self.sizes();
+        let mut lines = Vec::new();
+        self.visit(Self::ROOT, true, &mut |id, depth| {
+            let node = &self.nodes[id];
+            let kind = match node.kind {
+                NodeKind::File { size } => format!("file, size={}", size),
+                NodeKind::Directory { .. } => format!("dir, size={}", sizes[id]),
+            };
+            lines.push(format!("{}- {} ({})", "  ".repeat(depth), node.name, kind));
+        }, 0);
+        lines.join("\n")
+    }
+
+    /// size of each directory, children before their parent, as `du` prints them
+    pub fn du(&self, human_readable: bool) -> String {
+        let sizes = self.sizes();
+        let mut lines = Vec::new();
+        self.visit(Self::ROOT, false, &mut |id, _| {
+            if self.is_dir(id) {
+                let size = if human_readable { human_size(sizes[id]) } else { sizes[id].to_string() };
+                lines.push(format!("{}\t{}", size, self.path(id)));
+            }
+        }, 0);
+        lines.join("\n")
+    }
+
+    /// builds a copy of a real directory tree, with the apparent size of the
+    /// files; entries whose name can't appear in a transcript, symbolic links
+    /// and unreadable directories are skipped and returned as warnings
+    pub fn from_dir(path: &Path) -> AoCResult<(Self, Vec<String>)> {
+        let mut result = Self::default();
+        let mut warnings = Vec::new();
+        result.copy_dir(path, Self::ROOT, &mut warnings)?;
+        Ok((result, warnings))
+    }
+
+    fn copy_dir(&mut self, path: &Path, dir: NodeId, warnings: &mut Vec<String>) -> AoCResult<()> {
+        let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("{}: {}", path.display(), e))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let entry_path = entry.path();
+            if name.contains(char::is_whitespace) || name.contains('/') {
+                warnings.push(format!("{}: name not supported in a transcript", entry_path.display()));
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    warnings.push(format!("{}: {}", entry_path.display(), e));
+                    continue;
+                },
+            };
+            if metadata.is_dir() {
+                let child = self.mkdir(dir, &name)?;
+                if let Err(e) = self.copy_dir(&entry_path, child, warnings) {
+                    warnings.push(e);
+                }
+            } else if metadata.is_file() {
+                self.touch(dir, &name, metadata.len() as usize)?;
+            } else {
+                warnings.push(format!("{}: not a regular file or directory", entry_path.display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// random tree with the given number of directories and files, each file
+    /// with a size in `1..=max_file_size`
+    pub fn random(seed: u64, directories: usize, files: usize, max_file_size: usize) -> Self {
+        fn random_name(rng: &mut Rng, with_extension: bool) -> String {
+            let mut name: String = (0..rng.range(1, 8)).map(|_| (b'a' + rng.range(0, 25) as u8) as char).collect();
+            if with_extension {
+                name.push('.');
+                name.extend((0..3).map(|_| (b'a' + rng.range(0, 25) as u8) as char));
+            }
+            name
+        }
+
+        let mut rng = Rng::new(seed);
+        let mut result = Self::default();
+        let mut dirs = vec![Self::ROOT];
+        while dirs.len() <= directories {
+            let parent = dirs[rng.index(dirs.len())];
+            let name = random_name(&mut rng, false);
+            if result.child(parent, &name).is_none() {
+                dirs.push(result.mkdir(parent, &name).unwrap());
+            }
+        }
+        let mut created = 0;
+        while created < files {
+            let dir = dirs[rng.index(dirs.len())];
+            let with_extension = rng.bool();
+            let name = random_name(&mut rng, with_extension);
+            if result.child(dir, &name).is_none() {
+                result.touch(dir, &name, rng.range(1, max_file_size as u64) as usize).unwrap();
+                created += 1;
+            }
+        }
+        result
+    }
+
+    /// terminal session exploring the whole tree, in the format of the day 7 input
+    pub fn transcript(&self) -> String {
+        let mut lines = vec!["$ cd /".to_string()];
+        self.explore(Self::ROOT, &mut lines);
+        lines.join("\n")
+    }
+
+    fn explore(&self, dir: NodeId, lines: &mut Vec<String>) {
+        let children = match &self.nodes[dir].kind {
+            NodeKind::Directory { children } => children,
+            NodeKind::File { .. } => return,
+        };
+        lines.push("$ ls".into());
+        for (name, id) in children {
+            match self.nodes[*id].kind {
+                NodeKind::File { size } => lines.push(format!("{} {}", size, name)),
+                NodeKind::Directory { .. } => lines.push(format!("dir {}", name)),
+            }
+        }
+        for (name, id) in children {
+            if self.is_dir(*id) {
+                lines.push(format!("$ cd {}", name));
+                self.explore(*id, lines);
+                lines.push("$ cd ..".into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileSystem;
+
+    #[test]
+    fn test_resolve() {
+        let mut fs = FileSystem::default();
+        let a = fs.mkdir(FileSystem::ROOT, "a").unwrap();
+        let b = fs.mkdir(a, "b").unwrap();
+        let c = fs.mkdir(a, "c").unwrap();
+        assert_eq!(fs.resolve(FileSystem::ROOT, "/a/b/../c"), Ok(c));
+        assert_eq!(fs.resolve(b, "../c"), Ok(c));
+        assert_eq!(fs.resolve(c, "/"), Ok(FileSystem::ROOT));
+        assert_eq!(fs.resolve(b, "/a/b/../../.."), Err("/a/b/../../..: can't go above the root directory".into()));
+        assert_eq!(fs.resolve(FileSystem::ROOT, ".."), Err("..: can't go above the root directory".into()));
+    }
+
+    #[test]
+    fn test_touch_conflict() {
+        let mut fs = FileSystem::default();
+        let file = fs.touch(FileSystem::ROOT, "f.txt", 10).unwrap();
+        assert_eq!(fs.touch(FileSystem::ROOT, "f.txt", 10), Ok(file));
+        assert_eq!(fs.touch(FileSystem::ROOT, "f.txt", 20), Err("/f.txt already exists with size 10, not 20".into()));
+        assert_eq!(fs.mkdir(FileSystem::ROOT, "f.txt"), Err("/f.txt already exists and is not a directory".into()));
+    }
+}
@@ -1,19 +1,35 @@
 mod problem;
 mod days;
+mod filesystem;
+mod random;
+mod cpu;
+mod ocr;
+mod bigint;
+mod packet;
 
-use std::fs::File;
-use std::io::{BufReader, BufRead};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
 use std::time::Instant;
 
-use problem::AoCProblem;
+use filesystem::FileSystem;
+use problem::{Animation, AoCProblem, AoCResult};
 
-use clap::Parser;
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// day to solve
-    day: u32,
+    #[arg(required = true)]
+    day: Option<u32>,
 
     /// solve example code
     #[arg(short, long)]
@@ -25,20 +41,172 @@ struct Args {
 
     /// dump the input
     #[arg(short, long)]
-    dump_input: bool
+    dump_input: bool,
+
+    /// show a detailed report of the solution
+    #[arg(short, long)]
+    report: bool,
+
+    /// show the state of the machine at each step of the solution
+    #[arg(long)]
+    trace: bool,
+
+    /// day specific option, in the form key=value (can be repeated)
+    #[arg(short, long = "option", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+
+    /// read the input from this file instead of the one of the day
+    #[arg(short, long, conflicts_with = "example")]
+    input: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// generate a day 7 terminal transcript from a directory tree
+    Transcript {
+        /// directory to explore, a random tree is generated when missing
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// seed of the random tree
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+
+        /// number of directories of the random tree
+        #[arg(long, default_value_t = 200, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        directories: usize,
+
+        /// number of files of the random tree
+        #[arg(long, default_value_t = 300, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        files: usize,
+
+        /// maximum size of the files of the random tree
+        #[arg(long, default_value_t = 300_000, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        max_file_size: usize,
+
+        /// file where the transcript is written, standard output when missing
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// generate a day 10 program drawing an image on the CRT
+    Compile {
+        /// picture of the display, 6 lines of 40 pixels where `#` is lit
+        image: PathBuf,
+
+        /// file where the program is written, standard output when missing
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// print a day 10 program with the cycles and register changes of each instruction
+    Disassemble {
+        /// source code of the program
+        program: PathBuf,
+    },
+}
+
+fn read_file(path: &Path) -> AoCResult<String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn exit_with_error(message: String) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+/// shows the frames one after the other, each one replacing the previous
+/// one below the text already printed
+fn play(animation: &Animation) {
+    let mut previous_lines = 0;
+    for frame in &animation.frames {
+        if previous_lines > 0 {
+            // move the cursor up to the start of the previous frame and clear from there
+            print!("\x1b[{}A\x1b[J", previous_lines);
+        }
+        println!("{}", frame);
+        previous_lines = frame.lines().count();
+        thread::sleep(animation.delay);
+    }
+}
+
+fn set_options(problem: &mut dyn AoCProblem, options: &[String]) -> AoCResult<()> {
+    for option in options {
+        match option.split_once('=') {
+            Some((key, value)) => problem.set_option(key.trim(), value.trim())?,
+            None => problem.set_option(option.trim(), "")?,
+        }
+    }
+    Ok(())
+}
+
+fn run_command(command: Command) -> AoCResult<()> {
+    match command {
+        Command::Transcript { dir, seed, directories, files, max_file_size, output } => {
+            let fs = match dir {
+                Some(dir) => {
+                    let (fs, warnings) = FileSystem::from_dir(&dir)?;
+                    for warning in warnings {
+                        eprintln!("warning: skipped {}", warning);
+                    }
+                    fs
+                },
+                None => FileSystem::random(seed, directories, files, max_file_size),
+            };
+            let transcript = fs.transcript();
+            match output {
+                Some(path) => fs::write(&path, transcript + "\n").map_err(|e| format!("{}: {}", path.display(), e))?,
+                None => println!("{}", transcript),
+            }
+
+            // answers computed on the tree itself, without the day 7 solution, to cross check it
+            eprintln!("expected part 1 = {}", fs.walk_part_1(days::day07::MAX_SIZE));
+            match fs.walk_part_2(days::day07::TOTAL_SIZE, days::day07::REQUIRED_SPACE) {
+                Ok(result) => eprintln!("expected part 2 = {}", result),
+                Err(e) => eprintln!("expected part 2 = error: {}", e),
+            }
+            Ok(())
+        },
+        Command::Compile { image, output } => {
+            let image = days::day10::parse_image(&read_file(&image)?)?;
+            let listing = cpu::listing(&days::day10::compile_image(&image)?);
+            match output {
+                Some(path) => fs::write(&path, listing + "\n").map_err(|e| format!("{}: {}", path.display(), e))?,
+                None => println!("{}", listing),
+            }
+            match ocr::recognize(&image) {
+                Ok(letters) => eprintln!("expected part 2 = {}", letters),
+                Err(e) => eprintln!("expected part 2 = error: {}", e),
+            }
+            Ok(())
+        },
+        Command::Disassemble { program } => {
+            let program = cpu::assemble(&read_file(&program)?)?;
+            println!("{}", cpu::disassemble(&program));
+            Ok(())
+        },
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
+    if let Some(command) = args.command {
+        if let Err(e) = run_command(command) {
+            exit_with_error(e);
+        }
+        return
+    }
+
+    let day = args.day.unwrap();
     if args.template {
-        problem::create_template(args.day).unwrap();
+        problem::create_template(day).unwrap();
         return
     }
 
-    println!("*** solving day {} ***", args.day);
+    println!("*** solving day {} ***", day);
 
-    let mut problem: Box<dyn AoCProblem> = match args.day {
+    let mut problem: Box<dyn AoCProblem> = match day {
         1 => Box::new(days::day01::AoCDay1::default()),
         2 => Box::new(days::day02::AoCDay2::default()),
         3 => Box::new(days::day03::AoCDay3::default()),
@@ -57,31 +225,55 @@ fn main() {
         _ => panic!("day not yet implemented"),
     };
 
-    let input_path = if args.example {
-        format!("input/{:02}/example.txt", args.day)
+    if let Err(e) = set_options(problem.as_mut(), &args.options) {
+        exit_with_error(e);
+    }
+
+    let input_path = if let Some(path) = args.input {
+        path
+    } else if args.example {
+        PathBuf::from(format!("input/{:02}/example.txt", day))
     } else {
-        format!("input/{:02}/input.txt", args.day)
+        PathBuf::from(format!("input/{:02}/input.txt", day))
     };
 
-    let file = File::open(input_path)
-        .expect("error opening file");
-    let buffer_reader = BufReader::new(file);
-    for line in buffer_reader.lines() {
-        match line {
-            Ok(line) => problem.parse_line(line),
-            Err(_) => break,
-        }
+    let file = File::open(&input_path)
+        .unwrap_or_else(|e| exit_with_error(format!("{}: {}", input_path.display(), e)));
+    if let Err(e) = problem.parse_reader(&mut BufReader::new(file)) {
+        exit_with_error(e);
     }
 
     if args.dump_input {
         println!("PARSED INPUT: {:#?}", problem);
     }
 
-    let time_1 = Instant::now();
-    let result_1 = problem.solve_part1();
-    println!("DAY{} PART 1 solution = {} ({}ms)", args.day, result_1, time_1.elapsed().as_millis());
+    // a failed part still lets the report and the trace help find out why
+    let mut failed = false;
+    for (part, solve) in [(1, AoCProblem::solve_part1 as fn(&_) -> _), (2, AoCProblem::solve_part2)] {
+        let time = Instant::now();
+        match solve(problem.as_ref()) {
+            Ok(result) => println!("DAY{} PART {} solution = {} ({}ms)", day, part, result, time.elapsed().as_millis()),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                failed = true;
+            },
+        }
+    }
 
-    let time_2 = Instant::now();
-    let result_2 = problem.solve_part2();
-    println!("DAY{} PART 2 solution = {} ({}ms)", args.day, result_2, time_2.elapsed().as_millis());
+    if args.report {
+        let report = problem.report().unwrap_or_else(|e| exit_with_error(e));
+        println!("DAY{} REPORT:\n{}", day, report);
+        if let Some(animation) = problem.animation().unwrap_or_else(|e| exit_with_error(e)) {
+            play(&animation);
+        }
+    }
+
+    if args.trace {
+        let trace = problem.trace().unwrap_or_else(|e| exit_with_error(e));
+        println!("DAY{} TRACE:\n{}", day, trace);
+    }
+
+    if failed {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,183 @@
+use crate::problem::AoCResult;
+
+/// letters drawn on a display 6 pixels high, most 4 wide, each in a cell of 5 columns
+const SMALL_CELL_WIDTH: usize = 5;
+const SMALL_FONT: [(char, [&str; 6]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// letters drawn on a display 10 pixels high, 6 wide, each in a cell of 8 columns
+const LARGE_CELL_WIDTH: usize = 8;
+const LARGE_FONT: [(char, [&str; 10]); 15] = [
+    ('A', ["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', ["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', [".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('E', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', [".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', ["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('J', ["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', ["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', ["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('N', ["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('P', ["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('R', ["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('X', ["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Z', ["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+/// pixels of an image, indexed by row and then column
+pub type Image = Vec<Vec<bool>>;
+
+fn glyph(rows: &[&str]) -> Image {
+    rows.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect()
+}
+
+/// Splits an image in the groups of adjacent columns with at least one lit
+/// pixel, returning each letter as the list of its columns.
+fn split_letters(image: &[Vec<bool>]) -> Vec<Vec<Vec<bool>>> {
+    let width = image.iter().map(Vec::len).max().unwrap_or(0);
+    let column = |x: usize| -> Vec<bool> { image.iter().map(|row| row.get(x).copied().unwrap_or(false)).collect() };
+
+    let mut letters = Vec::new();
+    let mut current: Vec<Vec<bool>> = Vec::new();
+    for x in 0..width {
+        let col = column(x);
+        if col.contains(&true) {
+            current.push(col);
+        } else if !current.is_empty() {
+            letters.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        letters.push(current);
+    }
+    letters
+}
+
+/// Splits an image in cells of `width` columns, as the letters are laid out on
+/// the display, returning the lit columns of each cell that isn't blank. Unlike
+/// `split_letters`, this works for letters as wide as the cell, that touch the
+/// next one.
+fn split_cells(image: &[Vec<bool>], width: usize) -> Vec<Vec<Vec<bool>>> {
+    let image_width = image.iter().map(Vec::len).max().unwrap_or(0);
+    (0..image_width)
+        .step_by(width)
+        .map(|x| {
+            let cell: Vec<Vec<bool>> = image
+                .iter()
+                .map(|row| row.iter().skip(x).take(width).copied().collect())
+                .collect();
+            split_letters(&cell).concat()
+        })
+        .filter(|letter| !letter.is_empty())
+        .collect()
+}
+
+/// Reads the letters drawn on a display, choosing the font by the height of the
+/// image; unknown letters are an error. The letters are expected in fixed
+/// cells, and are split on the blank columns between them if they don't fit.
+pub fn recognize(image: &[Vec<bool>]) -> AoCResult<String> {
+    // letters are compared by columns, so that the blank columns on their sides don't matter
+    let columns = |rows: &[&str]| split_letters(&glyph(rows)).concat();
+    let (font, cell_width) = match image.len() {
+        6 => (SMALL_FONT.iter().map(|(c, rows)| (*c, columns(rows))).collect::<Vec<_>>(), SMALL_CELL_WIDTH),
+        10 => (LARGE_FONT.iter().map(|(c, rows)| (*c, columns(rows))).collect::<Vec<_>>(), LARGE_CELL_WIDTH),
+        height => return Err(format!("no font for an image {} pixels high (expected 6 or 10)", height)),
+    };
+
+    let read = |letters: Vec<Vec<Vec<bool>>>| -> AoCResult<String> {
+        let mut result = String::new();
+        for (i, letter) in letters.iter().enumerate() {
+            let c = font
+                .iter()
+                .find(|(_, columns)| columns == letter)
+                .map(|(c, _)| *c)
+                .ok_or_else(|| format!("unknown letter at position {}", i + 1))?;
+            result.push(c);
+        }
+        if result.is_empty() {
+            return Err("no letters in the image".into());
+        }
+        Ok(result)
+    };
+    read(split_cells(image, cell_width)).or_else(|e| read(split_letters(image)).map_err(|_| e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph, recognize, LARGE_CELL_WIDTH, LARGE_FONT, SMALL_CELL_WIDTH, SMALL_FONT};
+
+    /// draws the letters of a font next to each other, separated by `spacing` blank columns
+    fn draw(letters: &[&[&str]], spacing: usize) -> Vec<Vec<bool>> {
+        let height = letters[0].len();
+        let mut image = vec![Vec::new(); height];
+        for rows in letters {
+            for (y, row) in glyph(rows).into_iter().enumerate() {
+                image[y].extend(row);
+                image[y].extend(vec![false; spacing]);
+            }
+        }
+        image
+    }
+
+    /// draws the letters of a font in cells of `width` columns, like on the display
+    fn draw_cells(letters: &[&[&str]], width: usize) -> Vec<Vec<bool>> {
+        let mut image = draw(letters, 0);
+        let mut x = 0;
+        for rows in letters {
+            let letter_width = rows[0].len();
+            for row in image.iter_mut() {
+                row.splice(x + letter_width..x + letter_width, vec![false; width - letter_width]);
+            }
+            x += width;
+        }
+        image
+    }
+
+    #[test]
+    fn test_recognize_cells() {
+        let letter = |c: char| -> &[&str] { &SMALL_FONT.iter().find(|(l, _)| *l == c).unwrap().1 };
+        let image = draw_cells(&[letter('H'), letter('Y'), letter('H'), letter('Y'), letter('Z')], SMALL_CELL_WIDTH);
+        assert_eq!(image[0].len(), 5 * SMALL_CELL_WIDTH);
+        assert_eq!(recognize(&image), Ok("HYHYZ".into()));
+
+        let large: Vec<&[&str]> = LARGE_FONT.iter().map(|(_, rows)| &rows[..]).collect();
+        let expected: String = LARGE_FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize(&draw_cells(&large, LARGE_CELL_WIDTH)), Ok(expected));
+    }
+
+    #[test]
+    fn test_recognize_fonts() {
+        let small: Vec<&[&str]> = SMALL_FONT.iter().map(|(_, rows)| &rows[..]).collect();
+        let expected: String = SMALL_FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize(&draw(&small, 1)), Ok(expected));
+
+        let large: Vec<&[&str]> = LARGE_FONT.iter().map(|(_, rows)| &rows[..]).collect();
+        let expected: String = LARGE_FONT.iter().map(|(c, _)| c).collect();
+        assert_eq!(recognize(&draw(&large, 2)), Ok(expected));
+    }
+
+    #[test]
+    fn test_unknown_letter() {
+        let image = draw(&[&SMALL_FONT[0].1, &["####", "####", "####", "####", "####", "####"]], 1);
+        assert_eq!(recognize(&image), Err("unknown letter at position 2".into()));
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+/// Data of a packet of the distress signal: an integer or a list of items,
+/// written as a JSON array of non negative integers.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Item {
+    Integer(u32),
+    List(Vec<Item>),
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Integer(val) => write!(f, "{}", val),
+            Item::List(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// adds a line to the explanation of a comparison, if it's being traced
+fn trace(lines: &mut Option<&mut Vec<String>>, depth: usize, line: impl FnOnce() -> String) {
+    if let Some(lines) = lines {
+        lines.push(format!("{}- {}", "  ".repeat(depth), line()));
+    }
+}
+
+/// Compares two items as in the puzzle, without allocating anything unless the
+/// comparison is traced: an integer compared with a list is seen as a slice of
+/// a single element.
+fn compare_items(left: &Item, right: &Item, depth: usize, lines: &mut Option<&mut Vec<String>>) -> Ordering {
+    trace(lines, depth, || format!("Compare {} vs {}", left, right));
+    match (left, right) {
+        (Item::Integer(i1), Item::Integer(i2)) => {
+            let ordering = i1.cmp(i2);
+            match ordering {
+                Ordering::Less => trace(lines, depth + 1, || "Left side is smaller, so inputs are in the right order".into()),
+                Ordering::Greater => {
+                    trace(lines, depth + 1, || "Right side is smaller, so inputs are not in the right order".into())
+                },
+                Ordering::Equal => {},
+            }
+            ordering
+        },
+        (Item::List(l1), Item::List(l2)) => compare_lists(l1, l2, depth, lines),
+        (Item::Integer(_), Item::List(l2)) => {
+            trace(lines, depth + 1, || format!("Mixed types; convert left to [{}] and retry comparison", left));
+            trace(lines, depth + 1, || format!("Compare [{}] vs {}", left, right));
+            compare_lists(std::slice::from_ref(left), l2, depth + 1, lines)
+        },
+        (Item::List(l1), Item::Integer(_)) => {
+            trace(lines, depth + 1, || format!("Mixed types; convert right to [{}] and retry comparison", right));
+            trace(lines, depth + 1, || format!("Compare {} vs [{}]", left, right));
+            compare_lists(l1, std::slice::from_ref(right), depth + 1, lines)
+        },
+    }
+}
+
+fn compare_lists(l1: &[Item], l2: &[Item], depth: usize, lines: &mut Option<&mut Vec<String>>) -> Ordering {
+    for (i1, i2) in l1.iter().zip(l2) {
+        match compare_items(i1, i2, depth + 1, lines) {
+            Ordering::Equal => {},
+            ordering => return ordering,
+        }
+    }
+    let ordering = l1.len().cmp(&l2.len());
+    match ordering {
+        Ordering::Less => trace(lines, depth + 1, || "Left side ran out of items, so inputs are in the right order".into()),
+        Ordering::Greater => {
+            trace(lines, depth + 1, || "Right side ran out of items, so inputs are not in the right order".into())
+        },
+        Ordering::Equal => {},
+    }
+    ordering
+}
+
+impl Item {
+    /// compares two items, explaining each step like in the puzzle description
+    pub fn explain_cmp(&self, other: &Self) -> (Ordering, Vec<String>) {
+        let mut lines = Vec::new();
+        let ordering = compare_items(self, other, 0, &mut Some(&mut lines));
+        (ordering, lines)
+    }
+}
+
+/// Position, starting from 1, that each divider would have if it was sorted
+/// together with the packets, found without sorting them: it's the number of
+/// items less than the divider, plus the equal dividers that come before it,
+/// so it comes before the packets equal to it.
+pub fn rank(packets: &[Item], dividers: &[Item]) -> Vec<usize> {
+    let mut positions: Vec<usize> = dividers
+        .iter()
+        .enumerate()
+        .map(|(i, divider)| {
+            // equal dividers keep their order
+            let before = dividers[..i].iter().filter(|other| *other <= divider).count();
+            let after = dividers[i + 1..].iter().filter(|other| *other < divider).count();
+            1 + before + after
+        })
+        .collect();
+    // a single pass on the packets, which are usually many more than the dividers
+    for packet in packets {
+        for (position, divider) in positions.iter_mut().zip(dividers) {
+            if packet < divider {
+                *position += 1;
+            }
+        }
+    }
+    positions
+}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_items(self, other, 0, &mut None)
+    }
+}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Recursive descent parser of the packets, allowing whitespace between the
+/// tokens; errors report the column where they happen, starting from 1.
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("column {}: {}", self.position + 1, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.input.get(self.position).is_some_and(u8::is_ascii_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.input.get(self.position).copied()
+    }
+
+    fn item(&mut self) -> Result<Item, String> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b'0'..=b'9') => self.integer(),
+            Some(c) => Err(self.error(&format!("unexpected '{}', expected an integer or a list", c as char))),
+            None => Err(self.error("unexpected end of packet")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<Item, String> {
+        let start = self.position;
+        while self.input.get(self.position).is_some_and(u8::is_ascii_digit) {
+            self.position += 1;
+        }
+        let digits = std::str::from_utf8(&self.input[start..self.position]).unwrap();
+        match digits.parse() {
+            Ok(value) => Ok(Item::Integer(value)),
+            Err(_) => {
+                self.position = start;
+                Err(self.error(&format!("integer too large: {}", digits)))
+            },
+        }
+    }
+
+    fn list(&mut self) -> Result<Item, String> {
+        // skip the opening bracket
+        self.position += 1;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Item::List(items));
+        }
+        loop {
+            items.push(self.item()?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Item::List(items));
+                },
+                Some(c) => return Err(self.error(&format!("unexpected '{}', expected ',' or ']'", c as char))),
+                None => return Err(self.error("unexpected end of packet, expected ',' or ']'")),
+            }
+        }
+    }
+}
+
+impl FromStr for Item {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s.as_bytes(), position: 0 };
+        let item = parser.item()?;
+        match parser.peek() {
+            None => Ok(item),
+            Some(c) => Err(parser.error(&format!("unexpected '{}' after the end of the packet", c as char))),
+        }
+    }
+}
+
+impl From<&Item> for Value {
+    fn from(item: &Item) -> Self {
+        match item {
+            Item::Integer(value) => Value::from(*value),
+            Item::List(values) => Value::Array(values.iter().map(Value::from).collect()),
+        }
+    }
+}
+
+/// converts JSON data made only of arrays and integers that fit in an `u32`
+impl TryFrom<&Value> for Item {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(number) => number
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .map(Item::Integer)
+                .ok_or_else(|| format!("unsupported number: {}", number)),
+            Value::Array(values) => values.iter().map(Item::try_from).collect::<Result<_, _>>().map(Item::List),
+            _ => Err(format!("unsupported JSON value: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rank, Item};
+    use crate::random::Rng;
+    use std::cmp::Ordering;
+    use serde_json::Value;
+
+    const SEEDS: u64 = 500;
+
+    fn random_item(rng: &mut Rng, depth: usize) -> Item {
+        if depth == 0 || rng.range(0, 2) == 0 {
+            // mostly small values, sometimes up to the largest ones
+            let value = if rng.bool() { rng.range(0, 10) } else { rng.range(0, u32::MAX as u64) };
+            Item::Integer(value as u32)
+        } else {
+            Item::List((0..rng.range(0, 4)).map(|_| random_item(rng, depth - 1)).collect())
+        }
+    }
+
+    fn random_packet(rng: &mut Rng) -> Item {
+        Item::List((0..rng.range(0, 4)).map(|_| random_item(rng, 4)).collect())
+    }
+
+    /// the packet written with random whitespace between the tokens
+    fn with_whitespace(rng: &mut Rng, packet: &str) -> String {
+        let mut result = String::new();
+        let mut previous = None;
+        for c in packet.chars().chain(std::iter::once('\n')) {
+            // never split a number
+            if !(c.is_ascii_digit() && previous.is_some_and(|p: char| p.is_ascii_digit())) {
+                result.extend((0..rng.range(0, 2)).map(|_| if rng.bool() { ' ' } else { '\t' }));
+            }
+            result.push(c);
+            previous = Some(c);
+        }
+        result
+    }
+
+    #[test]
+    fn test_display_parse_round_trip() {
+        for seed in 0..SEEDS {
+            let mut rng = Rng::new(seed);
+            let packet = random_packet(&mut rng);
+            let text = packet.to_string();
+            assert_eq!(text.parse::<Item>(), Ok(packet.clone()), "seed {}", seed);
+
+            let spaced = with_whitespace(&mut rng, &text);
+            assert_eq!(spaced.parse::<Item>(), Ok(packet), "seed {}: {:?}", seed, spaced);
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        for seed in 0..SEEDS {
+            let packet = random_packet(&mut Rng::new(seed));
+            let json = Value::from(&packet);
+            assert_eq!(json.to_string(), packet.to_string(), "seed {}", seed);
+            assert_eq!(Item::try_from(&json), Ok(packet), "seed {}", seed);
+        }
+        let json: Value = serde_json::from_str("[1, -2]").unwrap();
+        assert!(Item::try_from(&json).is_err());
+    }
+
+    #[test]
+    fn test_rank_matches_sort() {
+        for seed in 0..SEEDS {
+            let mut rng = Rng::new(seed);
+            let packets: Vec<Item> = (0..rng.range(0, 30)).map(|_| random_packet(&mut rng)).collect();
+            let mut dividers: Vec<Item> = (0..rng.range(1, 4)).map(|_| random_packet(&mut rng)).collect();
+            // equal dividers too, sometimes
+            if rng.bool() {
+                dividers.push(dividers[0].clone());
+            }
+
+            // stable sort, with the dividers before the equal packets and in their order
+            let mut sorted: Vec<(&Item, Option<usize>)> = dividers.iter().enumerate().map(|(i, d)| (d, Some(i))).collect();
+            sorted.extend(packets.iter().map(|p| (p, None)));
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            let expected: Vec<usize> = (0..dividers.len())
+                .map(|i| sorted.iter().position(|(_, d)| *d == Some(i)).unwrap() + 1)
+                .collect();
+            assert_eq!(rank(&packets, &dividers), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_explain_cmp() {
+        let left: Item = "[[1],[2,3,4]]".parse().unwrap();
+        let right: Item = "[[1],4]".parse().unwrap();
+        let expected = [
+            "- Compare [[1],[2,3,4]] vs [[1],4]",
+            "  - Compare [1] vs [1]",
+            "    - Compare 1 vs 1",
+            "  - Compare [2,3,4] vs 4",
+            "    - Mixed types; convert right to [4] and retry comparison",
+            "    - Compare [2,3,4] vs [4]",
+            "      - Compare 2 vs 4",
+            "        - Left side is smaller, so inputs are in the right order",
+        ];
+        assert_eq!(left.explain_cmp(&right), (Ordering::Less, expected.map(String::from).to_vec()));
+
+        let left: Item = "[7,7,7,7]".parse().unwrap();
+        let right: Item = "[7,7,7]".parse().unwrap();
+        let (ordering, lines) = left.explain_cmp(&right);
+        assert_eq!(ordering, Ordering::Greater);
+        assert_eq!(lines.last().unwrap(), "  - Right side ran out of items, so inputs are not in the right order");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("[1,2", "column 5: unexpected end of packet, expected ',' or ']'"),
+            ("[1,,2]", "column 4: unexpected ',', expected an integer or a list"),
+            ("[1] 2", "column 5: unexpected '2' after the end of the packet"),
+            ("[99999999999]", "column 2: integer too large: 99999999999"),
+            ("[1 2]", "column 4: unexpected '2', expected ',' or ']'"),
+            ("", "column 1: unexpected end of packet"),
+        ];
+        for (text, error) in cases {
+            assert_eq!(text.parse::<Item>(), Err(error.to_string()), "{:?}", text);
+        }
+    }
+}
@@ -1,12 +1,68 @@
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
 use std::fs::{self, File, OpenOptions};
 use std::path::Path;
 use std::fmt::Debug;
+use std::time::Duration;
+
+pub type AoCResult<T> = Result<T, String>;
+
+/// frames shown one after the other in the terminal, replacing each other
+#[derive(Debug)]
+pub struct Animation {
+    pub frames: Vec<String>,
+    pub delay: Duration,
+}
 
 pub trait AoCProblem: Debug {
-    fn parse_line(&mut self, line: String);
-    fn solve_part1(&self) -> String;
-    fn solve_part2(&self) -> String;
+    fn parse_line(&mut self, line: String) -> AoCResult<()>;
+    fn solve_part1(&self) -> AoCResult<String>;
+    fn solve_part2(&self) -> AoCResult<String>;
+
+    /// parse the whole input, by default line by line with `parse_line`
+    fn parse_reader(&mut self, reader: &mut dyn BufRead) -> AoCResult<()> {
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("line {}: {}", i + 1, e))?;
+            self.parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        Ok(())
+    }
+
+    /// set a day specific option, passed on the command line as `-o key=value`
+    fn set_option(&mut self, key: &str, _value: &str) -> AoCResult<()> {
+        Err(format!("unknown option: {}", key))
+    }
+
+    /// detailed explanation of the solution, shown with `--report`
+    fn report(&self) -> AoCResult<String> {
+        Err("no report available for this day".into())
+    }
+
+    /// animation played after the report, shown with `--report`
+    fn animation(&self) -> AoCResult<Option<Animation>> {
+        Ok(None)
+    }
+
+    /// step by step execution of the solution, shown with `--trace`
+    fn trace(&self) -> AoCResult<String> {
+        Err("no trace available for this day".into())
+    }
+}
+
+/// parses the value of an option that must be a number greater than 0
+pub fn parse_positive(key: &str, value: &str) -> AoCResult<usize> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!("invalid {}: {}", key, value)),
+        Ok(n) => Ok(n),
+    }
+}
+
+/// parses the value of a flag option, which is true when given without a value
+pub fn parse_bool(key: &str, value: &str) -> AoCResult<bool> {
+    match value {
+        "" | "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("invalid {}: {}", key, value)),
+    }
 }
 
 pub fn create_template(day: u32) -> io::Result<()> {
@@ -22,28 +78,29 @@ pub fn create_template(day: u32) -> io::Result<()> {
 
     let mut day_file = OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(format!("src/days/day{:02}.rs", day))?;
-    writeln!(day_file,"use crate::problem::AoCProblem;\n")?;
+    writeln!(day_file,"use crate::problem::{{AoCProblem, AoCResult}};\n")?;
     writeln!(day_file,"#[derive(Debug, Default)]")?;
     writeln!(day_file, "pub struct AoCDay{} {{\n}}", day)?;
-    writeln!(day_file, "")?;
+    writeln!(day_file)?;
     writeln!(day_file, "impl AoCProblem for AoCDay{} {{", day)?;
-    writeln!(day_file, "    fn parse_line(&mut self, line: String) {{")?;
+    writeln!(day_file, "    fn parse_line(&mut self, line: String) -> AoCResult<()> {{")?;
     writeln!(day_file, "        // TODO")?;
+    writeln!(day_file, "        Ok(())")?;
     writeln!(day_file, "    }}")?;
-    writeln!(day_file, "")?;
-    writeln!(day_file, "    fn solve_part1(&self) -> String {{")?;
-    writeln!(day_file, "        \"TODO\".into()")?;
+    writeln!(day_file)?;
+    writeln!(day_file, "    fn solve_part1(&self) -> AoCResult<String> {{")?;
+    writeln!(day_file, "        Ok(\"TODO\".into())")?;
     writeln!(day_file, "    }}")?;
-    writeln!(day_file, "")?;
-    writeln!(day_file, "    fn solve_part2(&self) -> String {{")?;
-    writeln!(day_file, "        \"TODO\".into()")?;
+    writeln!(day_file)?;
+    writeln!(day_file, "    fn solve_part2(&self) -> AoCResult<String> {{")?;
+    writeln!(day_file, "        Ok(\"TODO\".into())")?;
     writeln!(day_file, "    }}")?;
     writeln!(day_file, "}}")?;
 
     let mut mod_file = OpenOptions::new()
-        .write(true)
         .append(true)
         .open("src/days/mod.rs")?;
 
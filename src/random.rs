@@ -0,0 +1,41 @@
+/// Small xorshift64* pseudo random generator, to build reproducible inputs
+/// from a seed without depending on an external crate.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the state must never be zero, mix the seed so that close seeds give different sequences
+        Self {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// uniform value in `min..=max`
+    pub fn range(&mut self, min: u64, max: u64) -> u64 {
+        assert!(min <= max);
+        match (max - min).checked_add(1) {
+            Some(len) => min + self.next_u64() % len,
+            None => self.next_u64(),
+        }
+    }
+
+    /// uniform index in `0..len`
+    pub fn index(&mut self, len: usize) -> usize {
+        assert!(len > 0);
+        self.range(0, len as u64 - 1) as usize
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}